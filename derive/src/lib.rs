@@ -0,0 +1,93 @@
+//! The `#[derive(DylibApi)]` macro of `dynamite-lib`, enabled through its
+//! `derive` feature
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::{Data, DeriveInput, Field, Fields, LitStr, parse_macro_input, spanned::Spanned};
+
+/// Implements `dynamite::DylibApi` for a struct with named `extern "C" fn`
+/// fields, resolving each from the library by the name of the field, or
+/// the one given with `#[dylib(name = "...")]`. Every field type must
+/// implement `dynamite::FnPtr`; any other type is a compile error
+#[proc_macro_derive(DylibApi, attributes(dylib))]
+pub fn derive_dylib_api(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    data.fields.span(),
+                    "DylibApi needs a struct with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "DylibApi can only be derived for structs",
+            ));
+        }
+    };
+    let resolved = fields
+        .iter()
+        .map(|field| {
+            let ident = &field.ident;
+            let ty = &field.ty;
+            let name = symbol_name(field)?;
+            // Spanned on the field type, so a field that is not a function
+            // pointer is reported there as not implementing `FnPtr`
+            Ok(quote_spanned! {ty.span()=>
+                #ident: unsafe { lib.get_fn::<#ty>(#name)? }
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::dynamite::DylibApi for #ident #ty_generics #where_clause {
+            unsafe fn load(
+                lib: &::dynamite::DynamicLibrary,
+            ) -> ::core::result::Result<Self, ::dynamite::Error> {
+                ::core::result::Result::Ok(Self { #(#resolved,)* })
+            }
+        }
+    })
+}
+
+/// The name given with `#[dylib(name = "...")]`, otherwise the name of the
+/// field without any `r#` prefix
+fn symbol_name(field: &Field) -> syn::Result<LitStr> {
+    let mut name = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("dylib"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown dylib attribute, expected `name`"))
+            }
+        })?;
+    }
+    Ok(match (name, &field.ident) {
+        (Some(name), _) => name,
+        (None, Some(ident)) => {
+            let ident = ident.to_string();
+            let ident = ident.strip_prefix("r#").unwrap_or(&ident);
+            LitStr::new(ident, field.span())
+        }
+        (None, None) => return Err(syn::Error::new(field.span(), "unnamed field")),
+    })
+}
@@ -1,24 +1,28 @@
-use std::{mem, path::Path};
+use std::path::Path;
 
 use dynamite::DynamicLibrary;
 
 fn main() {
-    #[cfg(target_os = "macos")]
-    let path = "demo/target/release/libdemo.dylib";
-    #[cfg(target_os = "linux")]
-    let path = "demo/target/release/libdemo.so";
+    // Next to the example when it was copied there, otherwise wherever the
+    // demo crate was built, preferring the release profile
+    let filename = DynamicLibrary::library_filename("demo");
+    let target = Path::new(env!("CARGO_MANIFEST_DIR")).join("demo/target");
+    let libm = DynamicLibrary::open_beside_exe(&filename).or_else(|_| {
+        DynamicLibrary::open_first(&[
+            target.join("release").join(&filename),
+            target.join("debug").join(&filename),
+        ])
+    });
     // The math library does not need to be loaded since it is already
     // statically linked in
-    let libm = match DynamicLibrary::open(Some(Path::new(path))) {
-        Err(error) => panic!("Could not load self as module: {}", error),
+    let libm = match libm {
+        Err(error) => panic!("Could not load the demo library, build it first: {}", error),
         Ok(libm) => libm,
     };
 
-    let cosine_fn: extern "C" fn(libc::c_double) -> libc::c_double = unsafe {
-        match libm.symbol("cosine") {
-            Err(error) => panic!("Could not load function cos: {}", error),
-            Ok(cosine_fn) => mem::transmute::<*mut u8, extern "C" fn(f64) -> f64>(cosine_fn),
-        }
+    let cosine_fn = match unsafe { libm.get_fn::<extern "C" fn(f64) -> f64>("cosine") } {
+        Err(error) => panic!("Could not load function cos: {}", error),
+        Ok(cosine_fn) => cosine_fn,
     };
 
     let argument = 0.0;
@@ -0,0 +1,99 @@
+//! Reverse lookups from an address to the loaded image and export it
+//! belongs to
+
+use std::{
+    ffi::{CStr, OsStr},
+    mem,
+    os::unix::ffi::OsStrExt,
+    path::PathBuf,
+};
+
+/// What the loader knows about an address, see [`symbol_info`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolInfo {
+    /// The path of the image containing the address, as the loader reports
+    /// it
+    pub image: PathBuf,
+    /// The address the image is mapped at, that of its ELF or Mach-O header
+    pub image_base: usize,
+    /// The name of the exported symbol nearest below the address, if any
+    pub symbol: Option<String>,
+    /// The address of that symbol
+    pub symbol_address: Option<usize>,
+}
+
+/// Returns the name of the exported symbol nearest below `addr`, such as the
+/// function containing a code address, or `None` when no named symbol of a
+/// loaded image precedes it. glibc only reports a symbol whose extent covers
+/// `addr`, so addresses in padding or in local functions have no name there
+pub fn nearest_symbol(addr: *const ()) -> Option<String> {
+    unsafe {
+        let mut info = mem::zeroed::<libc::Dl_info>();
+        if libc::dladdr(addr.cast(), &mut info) == 0 || info.dli_sname.is_null() {
+            return None;
+        }
+        Some(
+            CStr::from_ptr(info.dli_sname)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+/// Returns the image containing `addr` and the exported symbol nearest
+/// below it, or `None` when no loaded image contains `addr`. The same
+/// caveats as for [`nearest_symbol`] apply to the symbol
+pub fn symbol_info(addr: *const ()) -> Option<SymbolInfo> {
+    unsafe {
+        let mut info = mem::zeroed::<libc::Dl_info>();
+        if libc::dladdr(addr.cast(), &mut info) == 0 || info.dli_fname.is_null() {
+            return None;
+        }
+        let symbol = (!info.dli_sname.is_null()).then(|| {
+            CStr::from_ptr(info.dli_sname)
+                .to_string_lossy()
+                .into_owned()
+        });
+        Some(SymbolInfo {
+            image: PathBuf::from(OsStr::from_bytes(CStr::from_ptr(info.dli_fname).to_bytes())),
+            image_base: info.dli_fbase as usize,
+            symbol_address: symbol.as_ref().map(|_| info.dli_saddr as usize),
+            symbol,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ptr;
+
+    use super::*;
+    use crate::{DynamicLibrary, test_support};
+
+    #[test]
+    fn test_nearest_symbol() {
+        // A fixture rather than libm, whose functions are IFUNCs or aliases
+        // of other exports on some systems
+        let path = test_support::build_library(
+            "nearest_symbol",
+            "int nearest_target(int x) { return x * 3 + 1; }",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        match unsafe { lib.symbol::<u8>("nearest_target") } {
+            Err(error) => panic!("Could not load function nearest_target: {}", error),
+            Ok(target) => {
+                let inside = target.wrapping_add(1).cast::<()>();
+                assert_eq!(
+                    nearest_symbol(target.cast()).as_deref(),
+                    Some("nearest_target")
+                );
+                assert_eq!(nearest_symbol(inside).as_deref(), Some("nearest_target"));
+            }
+        }
+        assert_eq!(nearest_symbol(ptr::null()), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Foreign functions bound lazily, on first call, in the style of delay
+//! loading
+
+/// Declares foreign functions of a library, opened and resolved the first
+/// time each function is called rather than at startup, so a program can
+/// start without an optional library and only fail where it is used. The
+/// functions are grouped into a module named after the library binding,
+/// which sees the items of the enclosing one, and become `unsafe fn`s
+/// callable from any thread.
+///
+/// The library is given as anything a [`Path`](std::path::Path) can be
+/// made from, evaluated when it is first opened. `on_missing` picks what a
+/// call does when the library cannot be opened, the function is not found
+/// or its address is null: `panic` panics with the error, while
+/// `result` makes every function return a `Result` holding it. The outcome
+/// of opening the library and of resolving each function is kept, so a
+/// missing library is looked for only once.
+///
+/// ```no_run
+/// dynamite::dylib_bindings! {
+///     pub mod math = "libm.so.6", on_missing = panic {
+///         pub fn cos(x: f64) -> f64;
+///         pub fn sqrt(x: f64) -> f64;
+///     }
+/// }
+///
+/// dynamite::dylib_bindings! {
+///     mod compression = "libz.so.1", on_missing = result {
+///         pub fn zlibVersion() -> *const std::ffi::c_char;
+///     }
+/// }
+///
+/// assert_eq!(unsafe { math::cos(0.0) }, 1.0);
+/// let has_zlib = unsafe { compression::zlibVersion() }.is_ok();
+/// ```
+#[macro_export]
+macro_rules! dylib_bindings {
+    (
+        $(#[$meta:meta])*
+        $vis:vis mod $module:ident = $library:expr, on_missing = $strategy:ident {
+            $(
+                $(#[$fn_meta:meta])*
+                $fn_vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)?;
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        $vis mod $module {
+            #[allow(unused_imports)]
+            use super::*;
+
+            fn __dylib_library(
+            ) -> ::core::result::Result<&'static $crate::DynamicLibrary, $crate::Error> {
+                static LIBRARY: ::std::sync::OnceLock<
+                    ::core::result::Result<$crate::DynamicLibrary, $crate::Error>,
+                > = ::std::sync::OnceLock::new();
+                LIBRARY
+                    .get_or_init(|| {
+                        $crate::DynamicLibrary::open(Some(::std::path::Path::new(&$library)))
+                    })
+                    .as_ref()
+                    .map_err(::core::clone::Clone::clone)
+            }
+
+            $(
+                $crate::__dylib_binding! {
+                    $strategy;
+                    $(#[$fn_meta])*
+                    $fn_vis fn $name($($arg: $ty),*) $(-> $ret)?
+                }
+            )*
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __dylib_binding {
+    (
+        panic;
+        $(#[$meta:meta])*
+        $vis:vis fn $name:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)?
+    ) => {
+        $(#[$meta])*
+        $vis unsafe fn $name($($arg: $ty),*) $(-> $ret)? {
+            match $crate::__dylib_resolve!($name($($ty),*) $(-> $ret)?) {
+                ::core::result::Result::Ok(function) => unsafe { function($($arg),*) },
+                ::core::result::Result::Err(error) => {
+                    panic!("could not bind `{}`: {}", stringify!($name), error)
+                }
+            }
+        }
+    };
+    (
+        result;
+        $(#[$meta:meta])*
+        $vis:vis fn $name:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)?
+    ) => {
+        $(#[$meta])*
+        $vis unsafe fn $name(
+            $($arg: $ty),*
+        ) -> ::core::result::Result<$crate::__dylib_return!($($ret)?), $crate::Error> {
+            $crate::__dylib_resolve!($name($($ty),*) $(-> $ret)?)
+                .map(|function| unsafe { function($($arg),*) })
+        }
+    };
+}
+
+/// Resolves `name` from the library of the enclosing binding, once
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __dylib_resolve {
+    ($name:ident($($ty:ty),*) $(-> $ret:ty)?) => {{
+        type Function = unsafe extern "C" fn($($ty),*) $(-> $ret)?;
+        static FUNCTION: ::std::sync::OnceLock<
+            ::core::result::Result<Function, $crate::Error>,
+        > = ::std::sync::OnceLock::new();
+        FUNCTION
+            .get_or_init(|| {
+                __dylib_library()
+                    .and_then(|lib| unsafe { lib.get_fn::<Function>(stringify!($name)) })
+            })
+            .clone()
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __dylib_return {
+    () => {
+        ()
+    };
+    ($ret:ty) => {
+        $ret
+    };
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use std::thread;
+
+    use crate::{Error, test_support};
+
+    crate::dylib_bindings! {
+        mod math = "libm.so.6", on_missing = panic {
+            pub fn cos(x: f64) -> f64;
+            pub fn pow(x: f64, y: f64) -> f64;
+        }
+    }
+
+    crate::dylib_bindings! {
+        mod checked_math = "libm.so.6", on_missing = result {
+            pub fn sqrt(x: f64) -> f64;
+            pub fn not_in_libm();
+        }
+    }
+
+    crate::dylib_bindings! {
+        mod absent = "libdynamite-absent.so", on_missing = result {
+            pub fn absent_function(x: i32) -> i32;
+        }
+    }
+
+    crate::dylib_bindings! {
+        mod null_valued = test_support::build_library(
+            "null_binding",
+            "__asm__(\".globl null_binding\\n.set null_binding, 0\");",
+            &[],
+        ), on_missing = result {
+            pub fn null_binding();
+        }
+    }
+
+    #[test]
+    fn test_dylib_bindings() {
+        thread::scope(|scope| {
+            for i in 0..4 {
+                scope.spawn(move || {
+                    assert_eq!(unsafe { math::cos(0.0) }, 1.0);
+                    assert_eq!(unsafe { math::pow(2.0, f64::from(i)) }, f64::from(1 << i));
+                });
+            }
+        });
+        assert_eq!(unsafe { checked_math::sqrt(9.0) }, Ok(3.0));
+        assert!(unsafe { checked_math::not_in_libm() }.is_err());
+
+        let missing = unsafe { absent::absent_function(1) };
+        assert!(matches!(missing, Err(Error::Loader { .. })));
+        assert_eq!(unsafe { absent::absent_function(2) }, missing);
+
+        assert_eq!(
+            unsafe { null_valued::null_binding() },
+            Err(Error::NullSymbol {
+                name: "null_binding".to_string()
+            })
+        );
+    }
+}
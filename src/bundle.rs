@@ -0,0 +1,254 @@
+//! Several libraries distributed as a single file
+//!
+//! A bundle is the magic `DYNBNDL1`, the number of libraries as a little
+//! endian `u32`, then for each library in load order: the length of its file
+//! name as a `u32`, the UTF-8 file name, the length of its contents as a
+//! `u64` and the contents. Lengths are little endian.
+
+use std::{
+    env,
+    ffi::{CString, OsString},
+    fs::{self, OpenOptions},
+    io::Write,
+    os::unix::{
+        ffi::OsStringExt,
+        fs::{OpenOptionsExt, PermissionsExt},
+    },
+    path::{Path, PathBuf},
+};
+
+use crate::{DynamicLibrary, Error, dl, options};
+
+const MAGIC: &[u8; 8] = b"DYNBNDL1";
+
+/// Writes the libraries at `libraries` into a bundle at `path`, to be
+/// loaded in that order by [`DynamicLibrary::open_bundle`]. Each library
+/// keeps its file name, which must be unique within the bundle
+pub fn pack_bundle(path: &Path, libraries: &[&Path]) -> Result<(), Error> {
+    let mut bundle = MAGIC.to_vec();
+    bundle.extend_from_slice(&(libraries.len() as u32).to_le_bytes());
+    for library in libraries {
+        let name = library
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| malformed(library, "library without a UTF-8 file name"))?;
+        let contents = fs::read(library).map_err(|err| Error::io(library, &err))?;
+        bundle.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        bundle.extend_from_slice(name.as_bytes());
+        bundle.extend_from_slice(&(contents.len() as u64).to_le_bytes());
+        bundle.extend_from_slice(&contents);
+    }
+    fs::write(path, bundle).map_err(|err| Error::io(path, &err))
+}
+
+impl DynamicLibrary {
+    /// Lazily opens every library of the bundle at `path`, in bundle order,
+    /// see [`pack_bundle`]. The libraries are extracted into a new directory
+    /// only the current user can access, which is removed again once they
+    /// are loaded or the load fails; the loaded images stay valid
+    pub fn open_bundle(path: &Path) -> Result<Vec<DynamicLibrary>, Error> {
+        let data = fs::read(path).map_err(|err| Error::io(path, &err))?;
+        let entries = parse(path, &data)?;
+        let dir = private_temp_dir()?;
+        let libs = entries
+            .iter()
+            .map(|&(name, contents)| {
+                let file = write_private(&dir, name, contents)?;
+                DynamicLibrary::open(Some(&file))
+            })
+            .collect();
+        // Removing the files does not affect the mappings of the loaded ones
+        let _ = fs::remove_dir_all(&dir);
+        libs
+    }
+
+    /// Lazily opens the library whose file contents are `bytes`, such as a
+    /// plugin embedded into the executable with `include_bytes!`, without
+    /// shipping it as a separate file. `name` must be a plain file name; it
+    /// is the name the loader and `/proc/self/maps` report.
+    ///
+    /// On Linux the contents are written into an anonymous in-memory file
+    /// (`memfd_create`) loaded through `/proc/self/fd`, so nothing touches
+    /// the disk; its descriptor stays open until the handle is closed.
+    /// Elsewhere they are written into a new directory only the current
+    /// user can access, which is removed again once the library is loaded
+    /// or fails to. Either way no file is left behind, so
+    /// [`DynamicLibrary::opened_path`] is `None` and the library cannot be
+    /// reopened
+    pub fn open_embedded(name: &str, bytes: &'static [u8]) -> Result<DynamicLibrary, Error> {
+        if name.is_empty() || name.contains(['/', '\0']) || name == "." || name == ".." {
+            return Err(Error::loader(format!(
+                "`{}` is not a plain file name",
+                name.escape_debug()
+            )));
+        }
+        let flags = options::default_binding();
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::fd::{AsRawFd, FromRawFd};
+
+            let memfd_name = CString::new(name).unwrap_or_default();
+            let fd = unsafe { libc::memfd_create(memfd_name.as_ptr(), libc::MFD_CLOEXEC) };
+            let path = Path::new(name);
+            if fd < 0 {
+                return Err(Error::io(path, &std::io::Error::last_os_error()));
+            }
+            let mut file = unsafe { fs::File::from_raw_fd(fd) };
+            file.set_permissions(fs::Permissions::from_mode(options::temp_config().mode))
+                .map_err(|err| Error::io(path, &err))?;
+            file.write_all(bytes).map_err(|err| Error::io(path, &err))?;
+            let fd_path = format!("/proc/self/fd/{}", file.as_raw_fd());
+            let handle = dl::open(Some(fd_path.as_ref()), flags)?;
+            let mut lib = DynamicLibrary::from_handle(handle, flags, None)?;
+            // The loader recognizes libraries by name first, so the
+            // descriptor is kept open for its number not to be reused by
+            // another embedded library while this one is loaded
+            lib.file = Some(file);
+            Ok(lib)
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let dir = private_temp_dir()?;
+            let handle = write_private(&dir, name, bytes)
+                .and_then(|file| dl::open(Some(file.as_os_str()), flags));
+            let _ = fs::remove_dir_all(&dir);
+            DynamicLibrary::from_handle(handle?, flags, None)
+        }
+    }
+}
+
+/// Writes `contents` into a new file `name` of `dir`, with the mode of the
+/// [`TempConfig`](crate::TempConfig), and returns its path
+fn write_private(dir: &Path, name: &str, contents: &[u8]) -> Result<PathBuf, Error> {
+    let file = dir.join(name);
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(options::temp_config().mode)
+        .open(&file)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|err| Error::io(&file, &err))?;
+    Ok(file)
+}
+
+fn malformed(path: &Path, reason: &str) -> Error {
+    Error::MalformedBundle {
+        path: path.to_path_buf(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Splits a bundle into its `(file name, contents)` entries
+fn parse<'a>(path: &Path, data: &'a [u8]) -> Result<Vec<(&'a str, &'a [u8])>, Error> {
+    let mut rest = data
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| malformed(path, "missing bundle magic"))?;
+    let mut take = |len: usize| {
+        if rest.len() < len {
+            return Err(malformed(path, "truncated bundle"));
+        }
+        let (head, tail) = rest.split_at(len);
+        rest = tail;
+        Ok(head)
+    };
+    let count = u32::from_le_bytes(take(4)?.try_into().unwrap_or_default());
+    (0..count)
+        .map(|_| {
+            let name_len = u32::from_le_bytes(take(4)?.try_into().unwrap_or_default());
+            let name = str::from_utf8(take(name_len as usize)?)
+                .map_err(|_| malformed(path, "library name is not UTF-8"))?;
+            if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+                return Err(malformed(path, "library name is not a plain file name"));
+            }
+            let len = u64::from_le_bytes(take(8)?.try_into().unwrap_or_default());
+            let len = usize::try_from(len).map_err(|_| malformed(path, "library too large"))?;
+            Ok((name, take(len)?))
+        })
+        .collect()
+}
+
+/// Creates a uniquely named directory accessible only to the current user,
+/// within the directory of the [`TempConfig`](crate::TempConfig)
+pub(crate) fn private_temp_dir() -> Result<PathBuf, Error> {
+    let parent = options::temp_config().dir.unwrap_or_else(env::temp_dir);
+    let template = parent.join("dynamite-bundle-XXXXXX");
+    let mut template = CString::new(template.into_os_string().into_vec())
+        .map_err(|_| Error::loader("temporary directory path contains a NUL byte"))?
+        .into_bytes_with_nul();
+    if unsafe { libc::mkdtemp(template.as_mut_ptr().cast()).is_null() } {
+        let err = std::io::Error::last_os_error();
+        return Err(Error::io(&parent, &err));
+    }
+    // mkdtemp creates the directory with mode 0700
+    template.pop();
+    Ok(PathBuf::from(OsString::from_vec(template)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn test_open_bundle() {
+        let first = test_support::build_library(
+            "bundled_first",
+            "int bundled_first(void) { return 1; }",
+            &[],
+        );
+        let second = test_support::build_library(
+            "bundled_second",
+            "int bundled_second(void) { return 2; }",
+            &[],
+        );
+        let bundle = test_support::fixture_dir().join("plugins.bundle");
+        if let Err(error) = pack_bundle(&bundle, &[&first, &second]) {
+            panic!("Could not pack the bundle: {}", error);
+        }
+
+        let libs = match DynamicLibrary::open_bundle(&bundle) {
+            Err(error) => panic!("Could not open the bundle: {}", error),
+            Ok(libs) => libs,
+        };
+        assert_eq!(libs.len(), 2);
+        for (lib, name) in libs.iter().zip(["bundled_first", "bundled_second"]) {
+            assert!(unsafe { lib.symbol::<u8>(name) }.is_ok());
+        }
+
+        match DynamicLibrary::open_bundle(&first) {
+            Err(Error::MalformedBundle { .. }) => {}
+            Err(error) => panic!("Expected a malformed bundle, got: {}", error),
+            Ok(_) => panic!("Opened a library as a bundle."),
+        }
+    }
+
+    #[test]
+    fn test_open_embedded() {
+        let path = test_support::build_library(
+            "embedded",
+            "int embedded_answer(void) { return 42; }",
+            &[],
+        );
+        // Stands in for `include_bytes!`, as the fixture is only built
+        // when the tests run
+        let bytes: &'static [u8] = match fs::read(&path) {
+            Err(error) => panic!("Could not read the fixture: {}", error),
+            Ok(bytes) => bytes.leak(),
+        };
+        let lib = match DynamicLibrary::open_embedded("libembedded.so", bytes) {
+            Err(error) => panic!("Could not open the embedded library: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(lib.opened_path(), None);
+        match unsafe { lib.symbol::<u8>("embedded_answer") } {
+            Err(error) => panic!("Could not find embedded_answer: {}", error),
+            Ok(symbol) => {
+                let answer =
+                    unsafe { std::mem::transmute::<*mut u8, extern "C" fn() -> i32>(symbol) };
+                assert_eq!(answer(), 42);
+            }
+        }
+        assert!(DynamicLibrary::open_embedded("../libembedded.so", bytes).is_err());
+        assert!(DynamicLibrary::open_embedded("libgarbage.so", b"not a library").is_err());
+    }
+}
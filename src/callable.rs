@@ -0,0 +1,271 @@
+//! Type-erased callables built with libffi, for plugin calls whose signature
+//! is only known at runtime
+
+use std::ffi::c_void;
+
+use libffi::middle::{Arg, Cif, CodePtr, Type, arg};
+
+use crate::{DynamicLibrary, Error};
+
+/// A C type taken or returned by a function called through
+/// [`DynamicLibrary::make_callable`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CType {
+    Void,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+    Pointer,
+}
+
+/// A C value passed to or returned from a function called through
+/// [`DynamicLibrary::make_callable`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CValue {
+    Void,
+    I8(i8),
+    U8(u8),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Pointer(*mut c_void),
+}
+
+impl CValue {
+    /// Returns the C type of this value
+    pub const fn ctype(&self) -> CType {
+        match self {
+            CValue::Void => CType::Void,
+            CValue::I8(_) => CType::I8,
+            CValue::U8(_) => CType::U8,
+            CValue::I16(_) => CType::I16,
+            CValue::U16(_) => CType::U16,
+            CValue::I32(_) => CType::I32,
+            CValue::U32(_) => CType::U32,
+            CValue::I64(_) => CType::I64,
+            CValue::U64(_) => CType::U64,
+            CValue::F32(_) => CType::F32,
+            CValue::F64(_) => CType::F64,
+            CValue::Pointer(_) => CType::Pointer,
+        }
+    }
+}
+
+/// The argument and return types of a C function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FnSignature {
+    pub args: Vec<CType>,
+    pub ret: CType,
+}
+
+impl FnSignature {
+    pub fn new(args: impl Into<Vec<CType>>, ret: CType) -> FnSignature {
+        FnSignature {
+            args: args.into(),
+            ret,
+        }
+    }
+}
+
+/// A resolved C function callable with [`CValue`] arguments, borrowing the
+/// library it was resolved from
+pub type Callable<'lib> = Box<dyn Fn(&[CValue]) -> CValue + Send + 'lib>;
+
+/// A prepared call interface. It owns the type descriptions it points to and
+/// holds no thread-affine state, so it can move to another thread
+struct SendCif(Cif);
+
+unsafe impl Send for SendCif {}
+
+impl SendCif {
+    // Accessed through a method so closures capture the whole wrapper rather
+    // than its non-`Send` field
+    const fn cif(&self) -> &Cif {
+        &self.0
+    }
+}
+
+impl DynamicLibrary {
+    /// Resolves the function `name` and wraps it in a closure that calls it
+    /// with the argument and return types described by `signature`.
+    ///
+    /// The closure panics if it is given arguments whose number or types do
+    /// not match `signature`. A null export fails with [`Error::NullSymbol`].
+    ///
+    /// # Safety
+    ///
+    /// `signature` must match the actual C signature of the function, and
+    /// the function must be safe to call with any argument values passed to
+    /// the closure.
+    pub unsafe fn make_callable(
+        &self,
+        name: &str,
+        signature: &FnSignature,
+    ) -> Result<Callable<'_>, Error> {
+        if signature.args.contains(&CType::Void) {
+            return Err(Error::InvalidSignature {
+                name: name.to_string(),
+                reason: "void is not an argument type".to_string(),
+            });
+        }
+        let code = unsafe { self.symbol::<c_void>(name)? };
+        if code.is_null() {
+            return Err(Error::NullSymbol {
+                name: name.to_string(),
+            });
+        }
+        // The address is kept as an integer so the closure stays `Send`
+        let code = code as usize;
+        let cif = SendCif(Cif::new(
+            signature.args.iter().map(|ctype| ffi_type(*ctype)),
+            ffi_type(signature.ret),
+        ));
+        let signature = signature.clone();
+
+        Ok(Box::new(move |args: &[CValue]| {
+            let types: Vec<CType> = args.iter().map(CValue::ctype).collect();
+            assert_eq!(
+                types, signature.args,
+                "arguments do not match the signature"
+            );
+            let args: Vec<Arg> = args.iter().map(ffi_arg).collect();
+            let code = CodePtr(code as *mut c_void);
+            let cif = cif.cif();
+            // Integral results narrower than a register are widened by
+            // libffi, so they are read back as a full `ffi_arg`
+            unsafe {
+                match signature.ret {
+                    CType::Void => {
+                        cif.call::<()>(code, &args);
+                        CValue::Void
+                    }
+                    CType::I8 => CValue::I8(cif.call::<usize>(code, &args) as i8),
+                    CType::U8 => CValue::U8(cif.call::<usize>(code, &args) as u8),
+                    CType::I16 => CValue::I16(cif.call::<usize>(code, &args) as i16),
+                    CType::U16 => CValue::U16(cif.call::<usize>(code, &args) as u16),
+                    CType::I32 => CValue::I32(cif.call::<usize>(code, &args) as i32),
+                    CType::U32 => CValue::U32(cif.call::<usize>(code, &args) as u32),
+                    CType::I64 => CValue::I64(cif.call(code, &args)),
+                    CType::U64 => CValue::U64(cif.call(code, &args)),
+                    CType::F32 => CValue::F32(cif.call(code, &args)),
+                    CType::F64 => CValue::F64(cif.call(code, &args)),
+                    CType::Pointer => CValue::Pointer(cif.call(code, &args)),
+                }
+            }
+        }))
+    }
+}
+
+fn ffi_type(ctype: CType) -> Type {
+    match ctype {
+        CType::Void => Type::void(),
+        CType::I8 => Type::i8(),
+        CType::U8 => Type::u8(),
+        CType::I16 => Type::i16(),
+        CType::U16 => Type::u16(),
+        CType::I32 => Type::i32(),
+        CType::U32 => Type::u32(),
+        CType::I64 => Type::i64(),
+        CType::U64 => Type::u64(),
+        CType::F32 => Type::f32(),
+        CType::F64 => Type::f64(),
+        CType::Pointer => Type::pointer(),
+    }
+}
+
+fn ffi_arg(value: &CValue) -> Arg {
+    match value {
+        CValue::Void => unreachable!("void arguments are rejected when the callable is built"),
+        CValue::I8(value) => arg(value),
+        CValue::U8(value) => arg(value),
+        CValue::I16(value) => arg(value),
+        CValue::U16(value) => arg(value),
+        CValue::I32(value) => arg(value),
+        CValue::U32(value) => arg(value),
+        CValue::I64(value) => arg(value),
+        CValue::U64(value) => arg(value),
+        CValue::F32(value) => arg(value),
+        CValue::F64(value) => arg(value),
+        CValue::Pointer(value) => arg(value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_make_callable() {
+        let libm = match DynamicLibrary::open(Some(Path::new("libm.so.6"))) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(libm) => libm,
+        };
+        let mut calls = std::collections::HashMap::new();
+        let cos = FnSignature::new([CType::F64], CType::F64);
+        let abs = FnSignature::new([CType::I32], CType::I32);
+        for (name, signature) in [("cos", &cos), ("abs", &abs)] {
+            match unsafe { libm.make_callable(name, signature) } {
+                Err(error) => panic!("Could not build a callable for {}: {}", name, error),
+                Ok(callable) => calls.insert(name, callable),
+            };
+        }
+
+        assert_eq!(calls["cos"](&[CValue::F64(0.0)]), CValue::F64(1.0));
+        assert_eq!(calls["abs"](&[CValue::I32(-7)]), CValue::I32(7));
+    }
+
+    #[test]
+    #[should_panic(expected = "arguments do not match the signature")]
+    #[cfg(target_os = "linux")]
+    fn test_make_callable_checks_arguments() {
+        let libm = match DynamicLibrary::open(Some(Path::new("libm.so.6"))) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(libm) => libm,
+        };
+        let signature = FnSignature::new([CType::F64], CType::F64);
+        match unsafe { libm.make_callable("cos", &signature) } {
+            Err(error) => panic!("Could not build a callable for cos: {}", error),
+            Ok(cos) => cos(&[CValue::I32(0)]),
+        };
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_make_callable_rejects_null() {
+        let path = test_support::build_library(
+            "null_callable",
+            "__asm__(\".globl null_callable\\n.set null_callable, 0\");",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        let signature = FnSignature::new([], CType::Void);
+        match unsafe { lib.make_callable("null_callable", &signature) } {
+            Err(error) => assert_eq!(
+                error,
+                Error::NullSymbol {
+                    name: "null_callable".to_string()
+                }
+            ),
+            Ok(_) => panic!("Built a callable for a null function."),
+        }
+    }
+}
@@ -0,0 +1,925 @@
+//! Minimal reader for ELF shared objects on disk, used by the diagnostics
+//! that need information the loader does not expose (runpaths, symbol tables)
+//!
+//! Only the parts of the format those diagnostics need are parsed, for both
+//! ELF classes and byte orders.
+
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, resolve_library_path};
+
+const ELF_HEADER_SIZE: usize = 64;
+
+const ET_EXEC: u16 = 2;
+const ET_DYN: u16 = 3;
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PT_NOTE: u32 = 4;
+
+const NT_GNU_ABI_TAG: u32 = 1;
+const ELF_NOTE_OS_LINUX: u32 = 0;
+
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+const DT_STRTAB: u64 = 5;
+const DT_RPATH: u64 = 15;
+const DT_RUNPATH: u64 = 29;
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_DYNSYM: u32 = 11;
+
+const SHN_UNDEF: u16 = 0;
+
+const STB_GLOBAL: u8 = 1;
+const STB_WEAK: u8 = 2;
+const STB_GNU_UNIQUE: u8 = 10;
+
+const STT_SECTION: u8 = 3;
+const STT_FILE: u8 = 4;
+
+const STV_DEFAULT: u8 = 0;
+const STV_PROTECTED: u8 = 3;
+
+/// Returns the directories embedded in the `DT_RUNPATH` (or, when absent,
+/// the legacy `DT_RPATH`) entry of the ELF object at `path`, with `$ORIGIN`
+/// expanded to the directory containing the object
+pub fn runpath(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    let elf = ElfFile::read(path)?;
+    let dynamic = elf.dynamic()?;
+    let entry = dynamic
+        .iter()
+        .find(|(tag, _)| *tag == DT_RUNPATH)
+        .or_else(|| dynamic.iter().find(|(tag, _)| *tag == DT_RPATH));
+    let Some(&(_, offset)) = entry else {
+        return Ok(Vec::new());
+    };
+    let runpath = elf.dynamic_string(&dynamic, offset)?;
+
+    let origin = fs::canonicalize(path)
+        .map_err(|err| Error::io(path, &err))?
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let origin = origin.to_string_lossy();
+    Ok(runpath
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| {
+            PathBuf::from(
+                dir.replace("${ORIGIN}", &origin)
+                    .replace("$ORIGIN", &origin),
+            )
+        })
+        .collect())
+}
+
+/// Returns the names of the libraries the ELF object at `path` declares as
+/// needed (`DT_NEEDED`), in load order
+pub fn dependencies(path: &Path) -> Result<Vec<String>, Error> {
+    let elf = ElfFile::read(path)?;
+    let dynamic = elf.dynamic()?;
+    dynamic
+        .iter()
+        .filter(|(tag, _)| *tag == DT_NEEDED)
+        .map(|&(_, offset)| elf.dynamic_string(&dynamic, offset))
+        .collect()
+}
+
+/// A library in the tree built by [`dependency_tree`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyNode {
+    /// The name the library is needed by, or the path it was given as for
+    /// the root
+    pub name: String,
+    /// The file the name resolves to, `None` if it could not be found
+    pub path: Option<PathBuf>,
+    /// Whether the library already appears among its own ancestors, in
+    /// which case its dependencies are not expanded again
+    pub cycle: bool,
+    /// The libraries this one needs, in load order
+    pub dependencies: Vec<DependencyNode>,
+}
+
+/// Builds the tree of the libraries the ELF object at `path` transitively
+/// needs, reading each of them from disk. Names are looked up in the runpath
+/// of the object needing them, then as [`resolve_library_path`] does, and a
+/// library needed by one of its own dependencies ends its branch
+pub fn dependency_tree(path: &Path) -> Result<DependencyNode, Error> {
+    let path = fs::canonicalize(path).map_err(|err| Error::io(path, &err))?;
+    let mut ancestors = Vec::new();
+    dependency_node(path.display().to_string(), path, &mut ancestors)
+}
+
+fn dependency_node(
+    name: String,
+    path: PathBuf,
+    ancestors: &mut Vec<PathBuf>,
+) -> Result<DependencyNode, Error> {
+    if ancestors.contains(&path) {
+        return Ok(DependencyNode {
+            name,
+            path: Some(path),
+            cycle: true,
+            dependencies: Vec::new(),
+        });
+    }
+    let runpath = runpath(&path)?;
+    ancestors.push(path.clone());
+    let dependencies = dependencies(&path)?
+        .into_iter()
+        .map(|needed| {
+            let found = runpath
+                .iter()
+                .map(|dir| dir.join(&needed))
+                .find(|candidate| candidate.is_file())
+                .and_then(|candidate| fs::canonicalize(candidate).ok());
+            let found = match found {
+                Some(found) => Some(found),
+                None => resolve_library_path(&needed)?,
+            };
+            match found {
+                Some(found) => dependency_node(needed, found, ancestors),
+                None => Ok(DependencyNode {
+                    name: needed,
+                    path: None,
+                    cycle: false,
+                    dependencies: Vec::new(),
+                }),
+            }
+        })
+        .collect::<Result<_, _>>();
+    ancestors.pop();
+    Ok(DependencyNode {
+        name,
+        path: Some(path),
+        cycle: false,
+        dependencies: dependencies?,
+    })
+}
+
+/// Returns the names of the symbols the ELF object at `path` exports through
+/// its dynamic symbol table, reading the file without loading it
+pub fn exported_symbols_file(path: &Path) -> Result<BTreeSet<String>, Error> {
+    Ok(ElfFile::read(path)?
+        .dynamic_symbols()?
+        .into_iter()
+        .filter(ElfSymbol::is_export)
+        .map(|symbol| symbol.name)
+        .collect())
+}
+
+/// Returns the names of the symbols the ELF object at `path` expects other
+/// objects to define: the undefined entries (`SHN_UNDEF`) of its dynamic
+/// symbol table, sorted. Binding the library eagerly fails if one of them
+/// that is not weak cannot be found in its scope
+pub fn undefined_symbols(path: &Path) -> Result<Vec<String>, Error> {
+    let mut names: Vec<String> = ElfFile::read(path)?
+        .dynamic_symbols()?
+        .into_iter()
+        .filter(|symbol| !symbol.is_defined() && !symbol.name.is_empty())
+        .map(|symbol| symbol.name)
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    Ok(names)
+}
+
+/// Compares the exports of two versions of a library, returning the symbols
+/// only `new` exports followed by the symbols only `old` exports, both sorted
+pub fn exports_added_removed(old: &Path, new: &Path) -> Result<(Vec<String>, Vec<String>), Error> {
+    let old = exported_symbols_file(old)?;
+    let new = exported_symbols_file(new)?;
+    let added = new.difference(&old).cloned().collect();
+    let removed = old.difference(&new).cloned().collect();
+    Ok((added, removed))
+}
+
+/// Reports whether the ELF object at `path` can be loaded at any address,
+/// and so be placed randomly by ASLR: true for shared objects and
+/// position-independent executables (`ET_DYN`), false for executables
+/// linked at a fixed address (`ET_EXEC`)
+pub fn is_position_independent(path: &Path) -> Result<bool, Error> {
+    let elf = ElfFile::read_header(path)?;
+    match elf.u16(16)? {
+        ET_DYN => Ok(true),
+        ET_EXEC => Ok(false),
+        _ => Err(elf.malformed("neither an executable nor a shared object")),
+    }
+}
+
+/// Returns the minimum Linux kernel version, as `(major, minor, patch)`,
+/// that the ELF object at `path` declares it needs in its GNU ABI tag note
+/// (`.note.ABI-tag`), or `None` if it carries no such note for Linux. Lets
+/// hosts tell an old kernel apart before the load fails with a vaguer error
+pub fn required_os_abi(path: &Path) -> Result<Option<(u32, u32, u32)>, Error> {
+    let elf = ElfFile::read(path)?;
+    for segment in elf.segments()? {
+        if segment.kind != PT_NOTE {
+            continue;
+        }
+        let end = elf.offset_in_file(segment.offset, segment.filesz)?;
+        let mut at = segment.offset;
+        while end.saturating_sub(at) >= 12 {
+            let (name_size, desc_size, kind) = (elf.u32(at)?, elf.u32(at + 4)?, elf.u32(at + 8)?);
+            let name_at = at + 12;
+            let desc_at = elf.offset_in_file(name_at, u64::from(name_size).next_multiple_of(4))?;
+            at = elf.offset_in_file(desc_at, u64::from(desc_size).next_multiple_of(4))?;
+            if kind != NT_GNU_ABI_TAG || name_size != 4 || desc_size < 16 {
+                continue;
+            }
+            if elf.bytes::<4>(name_at)? != *b"GNU\0" || elf.u32(desc_at)? != ELF_NOTE_OS_LINUX {
+                continue;
+            }
+            return Ok(Some((
+                elf.u32(desc_at + 4)?,
+                elf.u32(desc_at + 8)?,
+                elf.u32(desc_at + 12)?,
+            )));
+        }
+    }
+    Ok(None)
+}
+
+/// An entry of an ELF symbol table
+pub(crate) struct ElfSymbol {
+    pub(crate) name: String,
+    /// The address of the symbol, relative to the load base, or its offset
+    /// in the TLS block for a thread-local variable
+    pub(crate) value: u64,
+    /// The size of the object the symbol names, in bytes
+    pub(crate) size: u64,
+    pub(crate) info: u8,
+    pub(crate) other: u8,
+    pub(crate) shndx: u16,
+}
+
+impl ElfSymbol {
+    pub(crate) const fn binding(&self) -> u8 {
+        self.info >> 4
+    }
+
+    pub(crate) const fn kind(&self) -> u8 {
+        self.info & 0xf
+    }
+
+    pub(crate) const fn visibility(&self) -> u8 {
+        self.other & 0x3
+    }
+
+    pub(crate) const fn is_defined(&self) -> bool {
+        self.shndx != SHN_UNDEF
+    }
+
+    /// Whether the loader would let other objects bind to this symbol
+    pub(crate) const fn is_export(&self) -> bool {
+        self.is_defined()
+            && !self.name.is_empty()
+            && matches!(self.binding(), STB_GLOBAL | STB_WEAK | STB_GNU_UNIQUE)
+            && !matches!(self.kind(), STT_SECTION | STT_FILE)
+            && matches!(self.visibility(), STV_DEFAULT | STV_PROTECTED)
+    }
+}
+
+/// A section header of an ELF object
+struct Section {
+    kind: u32,
+    offset: u64,
+    size: u64,
+    link: u32,
+    entsize: u64,
+}
+
+/// A program header of an ELF object
+struct Segment {
+    kind: u32,
+    offset: u64,
+    vaddr: u64,
+    filesz: u64,
+}
+
+/// An ELF object read into memory
+pub(crate) struct ElfFile {
+    path: PathBuf,
+    data: Vec<u8>,
+    is_64: bool,
+    is_little_endian: bool,
+}
+
+impl ElfFile {
+    pub(crate) fn read(path: &Path) -> Result<ElfFile, Error> {
+        let data = fs::read(path).map_err(|err| Error::io(path, &err))?;
+        ElfFile::parse(path, data)
+    }
+
+    /// Reads only the ELF header of the object at `path`, enough to tell
+    /// which machine it targets without loading the whole file
+    pub(crate) fn read_header(path: &Path) -> Result<ElfFile, Error> {
+        let mut data = Vec::with_capacity(ELF_HEADER_SIZE);
+        File::open(path)
+            .and_then(|file| file.take(ELF_HEADER_SIZE as u64).read_to_end(&mut data))
+            .map_err(|err| Error::io(path, &err))?;
+        ElfFile::parse(path, data)
+    }
+
+    fn parse(path: &Path, data: Vec<u8>) -> Result<ElfFile, Error> {
+        let mut elf = ElfFile {
+            path: path.to_path_buf(),
+            data,
+            is_64: false,
+            is_little_endian: true,
+        };
+        if elf.data.get(..4) != Some(b"\x7fELF".as_slice()) {
+            return Err(elf.malformed("missing ELF magic"));
+        }
+        elf.is_64 = match elf.data.get(4) {
+            Some(1) => false,
+            Some(2) => true,
+            _ => return Err(elf.malformed("unknown ELF class")),
+        };
+        elf.is_little_endian = match elf.data.get(5) {
+            Some(1) => true,
+            Some(2) => false,
+            _ => return Err(elf.malformed("unknown ELF byte order")),
+        };
+        Ok(elf)
+    }
+
+    /// Whether the loader would accept both objects in the same process:
+    /// same class, byte order and machine
+    pub(crate) fn same_target(&self, other: &ElfFile) -> bool {
+        self.is_64 == other.is_64
+            && self.is_little_endian == other.is_little_endian
+            && matches!((self.u16(18), other.u16(18)), (Ok(a), Ok(b)) if a == b)
+    }
+
+    fn malformed(&self, reason: &str) -> Error {
+        Error::MalformedElf {
+            path: self.path.clone(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Adds `delta` to the file offset `base`, failing unless the sum lies
+    /// within the file. As the file fits in memory, adding the offset of a
+    /// field to the result cannot overflow
+    fn offset_in_file(&self, base: u64, delta: u64) -> Result<u64, Error> {
+        base.checked_add(delta)
+            .filter(|offset| usize::try_from(*offset).is_ok_and(|end| end <= self.data.len()))
+            .ok_or_else(|| self.malformed("offset out of bounds"))
+    }
+
+    fn bytes<const N: usize>(&self, offset: u64) -> Result<[u8; N], Error> {
+        usize::try_from(offset)
+            .ok()
+            .and_then(|start| self.data.get(start..start.checked_add(N)?))
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| self.malformed("truncated file"))
+    }
+
+    fn u16(&self, offset: u64) -> Result<u16, Error> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.is_little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: u64) -> Result<u32, Error> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.is_little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn u64(&self, offset: u64) -> Result<u64, Error> {
+        let bytes = self.bytes(offset)?;
+        Ok(if self.is_little_endian {
+            u64::from_le_bytes(bytes)
+        } else {
+            u64::from_be_bytes(bytes)
+        })
+    }
+
+    /// Reads an address-sized field: 4 bytes for ELFCLASS32, 8 for ELFCLASS64
+    fn word(&self, offset: u64) -> Result<u64, Error> {
+        if self.is_64 {
+            self.u64(offset)
+        } else {
+            self.u32(offset).map(u64::from)
+        }
+    }
+
+    /// Reads the NUL-terminated string starting at `offset`
+    fn string(&self, offset: u64) -> Result<String, Error> {
+        let bytes = usize::try_from(offset)
+            .ok()
+            .and_then(|start| self.data.get(start..))
+            .and_then(|tail| tail.split(|byte| *byte == 0).next())
+            .ok_or_else(|| self.malformed("string out of bounds"))?;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn segments(&self) -> Result<Vec<Segment>, Error> {
+        let (phoff, phentsize, phnum) = if self.is_64 {
+            (self.u64(32)?, self.u16(54)?, self.u16(56)?)
+        } else {
+            (u64::from(self.u32(28)?), self.u16(42)?, self.u16(44)?)
+        };
+        (0..u64::from(phnum))
+            .map(|i| {
+                let at = self.offset_in_file(phoff, i * u64::from(phentsize))?;
+                Ok(if self.is_64 {
+                    Segment {
+                        kind: self.u32(at)?,
+                        offset: self.u64(at + 8)?,
+                        vaddr: self.u64(at + 16)?,
+                        filesz: self.u64(at + 32)?,
+                    }
+                } else {
+                    Segment {
+                        kind: self.u32(at)?,
+                        offset: u64::from(self.u32(at + 4)?),
+                        vaddr: u64::from(self.u32(at + 8)?),
+                        filesz: u64::from(self.u32(at + 16)?),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn sections(&self) -> Result<Vec<Section>, Error> {
+        let (shoff, shentsize, shnum) = if self.is_64 {
+            (self.u64(40)?, self.u16(58)?, self.u16(60)?)
+        } else {
+            (u64::from(self.u32(32)?), self.u16(46)?, self.u16(48)?)
+        };
+        (0..u64::from(shnum))
+            .map(|i| {
+                let at = self.offset_in_file(shoff, i * u64::from(shentsize))?;
+                Ok(if self.is_64 {
+                    Section {
+                        kind: self.u32(at + 4)?,
+                        offset: self.u64(at + 24)?,
+                        size: self.u64(at + 32)?,
+                        link: self.u32(at + 40)?,
+                        entsize: self.u64(at + 56)?,
+                    }
+                } else {
+                    Section {
+                        kind: self.u32(at + 4)?,
+                        offset: u64::from(self.u32(at + 16)?),
+                        size: u64::from(self.u32(at + 20)?),
+                        link: self.u32(at + 24)?,
+                        entsize: u64::from(self.u32(at + 36)?),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the entries of the first symbol table section of type `kind`,
+    /// with their names read from the linked string table
+    fn symbol_table(&self, kind: u32) -> Result<Vec<ElfSymbol>, Error> {
+        let sections = self.sections()?;
+        let Some(table) = sections.iter().find(|section| section.kind == kind) else {
+            return Ok(Vec::new());
+        };
+        let Some(strings) = usize::try_from(table.link)
+            .ok()
+            .and_then(|link| sections.get(link))
+        else {
+            return Err(self.malformed("symbol table without a string table"));
+        };
+        let entry_size = match table.entsize {
+            0 if self.is_64 => 24,
+            0 => 16,
+            size => size,
+        };
+        (0..table.size / entry_size)
+            .map(|i| {
+                let at = self.offset_in_file(table.offset, i * entry_size)?;
+                let (name, value, size, info, other, shndx) = if self.is_64 {
+                    (
+                        self.u32(at)?,
+                        self.u64(at + 8)?,
+                        self.u64(at + 16)?,
+                        self.bytes::<1>(at + 4)?[0],
+                        self.bytes::<1>(at + 5)?[0],
+                        self.u16(at + 6)?,
+                    )
+                } else {
+                    (
+                        self.u32(at)?,
+                        u64::from(self.u32(at + 4)?),
+                        u64::from(self.u32(at + 8)?),
+                        self.bytes::<1>(at + 12)?[0],
+                        self.bytes::<1>(at + 13)?[0],
+                        self.u16(at + 14)?,
+                    )
+                };
+                Ok(ElfSymbol {
+                    name: self.string(self.offset_in_file(strings.offset, u64::from(name))?)?,
+                    value,
+                    size,
+                    info,
+                    other,
+                    shndx,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the dynamic symbol table (`.dynsym`), the symbols visible to
+    /// the loader
+    pub(crate) fn dynamic_symbols(&self) -> Result<Vec<ElfSymbol>, Error> {
+        self.symbol_table(SHT_DYNSYM)
+    }
+
+    /// Returns the full symbol table (`.symtab`), which also lists the local
+    /// and hidden symbols, empty when the object was stripped
+    pub(crate) fn symbols(&self) -> Result<Vec<ElfSymbol>, Error> {
+        self.symbol_table(SHT_SYMTAB)
+    }
+
+    /// Translates a virtual address into a file offset through the loadable
+    /// segments, as the dynamic section refers to tables by address
+    fn vaddr_to_offset(&self, vaddr: u64) -> Result<u64, Error> {
+        let segments = self.segments()?;
+        let Some(segment) = segments.iter().find(|segment| {
+            segment.kind == PT_LOAD
+                && vaddr
+                    .checked_sub(segment.vaddr)
+                    .is_some_and(|delta| delta < segment.filesz)
+        }) else {
+            return Err(self.malformed("address outside of the loadable segments"));
+        };
+        self.offset_in_file(segment.offset, vaddr - segment.vaddr)
+    }
+
+    /// Returns the `(tag, value)` entries of the dynamic section, or none if
+    /// the object is statically linked
+    pub(crate) fn dynamic(&self) -> Result<Vec<(u64, u64)>, Error> {
+        let segments = self.segments()?;
+        let Some(dynamic) = segments.iter().find(|segment| segment.kind == PT_DYNAMIC) else {
+            return Ok(Vec::new());
+        };
+        let entry_size = if self.is_64 { 16 } else { 8 };
+        let mut entries = Vec::new();
+        for i in 0..dynamic.filesz / entry_size {
+            let at = self.offset_in_file(dynamic.offset, i * entry_size)?;
+            let tag = self.word(at)?;
+            if tag == DT_NULL {
+                break;
+            }
+            let value = self.word(at + entry_size / 2)?;
+            entries.push((tag, value));
+        }
+        Ok(entries)
+    }
+
+    /// Reads a string at `offset` in the dynamic string table (`DT_STRTAB`)
+    pub(crate) fn dynamic_string(
+        &self,
+        dynamic: &[(u64, u64)],
+        offset: u64,
+    ) -> Result<String, Error> {
+        let Some(&(_, strtab)) = dynamic.iter().find(|(tag, _)| *tag == DT_STRTAB) else {
+            return Err(self.malformed("missing dynamic string table"));
+        };
+        self.string(self.offset_in_file(self.vaddr_to_offset(strtab)?, offset)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn test_runpath_expands_origin() {
+        let runpath_lib = test_support::build_library(
+            "runpath",
+            "int runpath_marker(void) { return 1; }",
+            &[
+                "-Wl,--enable-new-dtags",
+                "-Wl,-rpath,$ORIGIN/deps:/opt/plugins",
+            ],
+        );
+        let rpath_lib = test_support::build_library(
+            "rpath",
+            "int rpath_marker(void) { return 1; }",
+            &["-Wl,--disable-new-dtags", "-Wl,-rpath,${ORIGIN}/legacy"],
+        );
+        let origin = match fs::canonicalize(test_support::fixture_dir()) {
+            Err(error) => panic!("Could not canonicalize the fixture directory: {}", error),
+            Ok(origin) => origin,
+        };
+
+        match runpath(&runpath_lib) {
+            Err(error) => panic!("Could not read the runpath: {}", error),
+            Ok(dirs) => assert_eq!(dirs, [origin.join("deps"), PathBuf::from("/opt/plugins")]),
+        }
+        match runpath(&rpath_lib) {
+            Err(error) => panic!("Could not read the rpath: {}", error),
+            Ok(dirs) => assert_eq!(dirs, [origin.join("legacy")]),
+        }
+    }
+
+    #[test]
+    fn test_exports_added_removed() {
+        let old = test_support::build_library(
+            "exports_old",
+            "int kept(void) { return 1; }\n\
+             int dropped(void) { return 2; }\n\
+             static int internal(void) { return 3; }\n\
+             int uses_internal(void) { return internal(); }\n",
+            &[],
+        );
+        let new = test_support::build_library(
+            "exports_new",
+            "int kept(void) { return 1; }\n\
+             int introduced = 4;\n\
+             __attribute__((visibility(\"hidden\"))) int hidden(void) { return 5; }\n\
+             int uses_internal(void) { return hidden(); }\n",
+            &[],
+        );
+
+        match exported_symbols_file(&old) {
+            Err(error) => panic!("Could not read the exports: {}", error),
+            Ok(exports) => {
+                assert!(exports.contains("kept"));
+                assert!(exports.contains("dropped"));
+                assert!(!exports.contains("internal"));
+            }
+        }
+        match exports_added_removed(&old, &new) {
+            Err(error) => panic!("Could not compare the exports: {}", error),
+            Ok((added, removed)) => {
+                assert_eq!(added, ["introduced"]);
+                assert_eq!(removed, ["dropped"]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_undefined_symbols() {
+        let path = test_support::build_library(
+            "undefined_symbols",
+            "#include <unistd.h>\n\
+             void plugin_host_log(const char *message);\n\
+             int report(void) { plugin_host_log(\"hi\"); return getpid(); }\n",
+            &[],
+        );
+        match undefined_symbols(&path) {
+            Err(error) => panic!("Could not read the imports: {}", error),
+            Ok(imports) => {
+                assert!(imports.contains(&"getpid".to_string()));
+                assert!(imports.contains(&"plugin_host_log".to_string()));
+                assert!(!imports.contains(&"report".to_string()));
+                assert!(imports.is_sorted());
+            }
+        }
+    }
+
+    #[test]
+    fn test_dependency_tree() {
+        let dir = test_support::fixture_dir().join("dependency_tree");
+        let link = [
+            format!("-L{}", dir.display()),
+            "-Wl,-rpath,$ORIGIN".to_string(),
+        ];
+        let args = |needed: &'static str| [link[0].as_str(), link[1].as_str(), needed];
+        test_support::build_library_in(&dir, "leaf", "int leaf(void) { return 1; }", &[]);
+        test_support::build_library_in(
+            &dir,
+            "middle",
+            "int leaf(void);\nint middle(void) { return leaf(); }",
+            &args("-lleaf"),
+        );
+        let top = test_support::build_library_in(
+            &dir,
+            "top",
+            "int middle(void);\nint top(void) { return middle(); }",
+            &args("-lmiddle"),
+        );
+        // The first `cycle_b` only exists so that `cycle_a` can link to it
+        test_support::build_library_in(&dir, "cycle_b", "int cycle_b(void) { return 2; }", &[]);
+        let cycle_a = test_support::build_library_in(
+            &dir,
+            "cycle_a",
+            "int cycle_b(void);\nint cycle_a(void) { return cycle_b(); }",
+            &args("-lcycle_b"),
+        );
+        test_support::build_library_in(
+            &dir,
+            "cycle_b",
+            "int cycle_a(void);\nint cycle_b(void) { return cycle_a(); }",
+            &args("-lcycle_a"),
+        );
+
+        let fixtures = |node: &DependencyNode| -> Vec<DependencyNode> {
+            node.dependencies
+                .iter()
+                .filter(|dependency| {
+                    dependency
+                        .path
+                        .as_ref()
+                        .is_some_and(|path| path.starts_with(&dir))
+                })
+                .cloned()
+                .collect()
+        };
+        let tree = match dependency_tree(&top) {
+            Err(error) => panic!("Could not build the dependency tree: {}", error),
+            Ok(tree) => tree,
+        };
+        let middle = fixtures(&tree);
+        assert_eq!(middle.len(), 1);
+        assert_eq!(middle[0].name, "libmiddle.so");
+        let leaf = fixtures(&middle[0]);
+        assert_eq!(leaf.len(), 1);
+        assert_eq!(leaf[0].name, "libleaf.so");
+        assert!(fixtures(&leaf[0]).is_empty());
+
+        let tree = match dependency_tree(&cycle_a) {
+            Err(error) => panic!("Could not build the cyclic dependency tree: {}", error),
+            Ok(tree) => tree,
+        };
+        let cycle_b = fixtures(&tree);
+        assert_eq!(cycle_b.len(), 1);
+        assert!(!cycle_b[0].cycle);
+        let back = fixtures(&cycle_b[0]);
+        assert_eq!(back.len(), 1);
+        assert!(back[0].cycle);
+        assert_eq!(back[0].path, tree.path);
+        assert!(back[0].dependencies.is_empty());
+    }
+
+    #[test]
+    fn test_is_position_independent() {
+        let library =
+            test_support::build_library("position_independent", "int pic(void) { return 1; }", &[]);
+        assert_eq!(is_position_independent(&library), Ok(true));
+
+        // The same header claiming to be linked at a fixed address
+        let fixed = test_support::fixture_dir().join("fixed_address");
+        let written = fs::read(&library).and_then(|mut header| {
+            header.truncate(ELF_HEADER_SIZE);
+            header[16..18].copy_from_slice(&ET_EXEC.to_ne_bytes());
+            fs::write(&fixed, header)
+        });
+        if let Err(error) = written {
+            panic!("Could not write the crafted header: {}", error);
+        }
+        assert_eq!(is_position_independent(&fixed), Ok(false));
+    }
+
+    #[test]
+    fn test_required_os_abi() {
+        // Shaped like the note glibc's crt1.o gives executables
+        let tagged = test_support::build_library(
+            "abi_tagged",
+            r#"__asm__(".section .note.ABI-tag, \"a\", @note\n"
+                       ".balign 4\n"
+                       ".long 4, 16, 1\n"
+                       ".asciz \"GNU\"\n"
+                       ".long 0, 3, 2, 17\n"
+                       ".previous\n");
+               int tagged(void) { return 1; }"#,
+            &[],
+        );
+        let untagged =
+            test_support::build_library("abi_untagged", "int untagged(void) { return 1; }", &[]);
+        assert_eq!(required_os_abi(&tagged), Ok(Some((3, 2, 17))));
+        assert_eq!(required_os_abi(&untagged), Ok(None));
+
+        // The note segment moved to the end of the address space
+        let crafted = crafted(&tagged, "abi_tagged_crafted", |data| {
+            let (phoff, phentsize, phnum) = (
+                field::<8>(data, 32),
+                field::<2>(data, 54),
+                field::<2>(data, 56),
+            );
+            let Some(note) = (0..phnum)
+                .map(|i| phoff + i * phentsize)
+                .find(|at| field::<4>(data, *at) == PT_NOTE as usize)
+            else {
+                panic!("The fixture has no note segment");
+            };
+            data[note + 8..note + 16].copy_from_slice(&(u64::MAX - 8).to_ne_bytes());
+        });
+        match required_os_abi(&crafted) {
+            Err(Error::MalformedElf { .. }) => {}
+            Err(error) => panic!("Expected a malformed ELF error, got: {}", error),
+            Ok(version) => panic!("Read a note past the end of the file: {:?}", version),
+        }
+    }
+
+    /// Writes a copy of `library` named `name`, altered by `patch`
+    fn crafted(library: &Path, name: &str, patch: impl FnOnce(&mut [u8])) -> PathBuf {
+        let crafted = test_support::fixture_dir().join(name);
+        let written = fs::read(library).and_then(|mut data| {
+            patch(&mut data);
+            fs::write(&crafted, data)
+        });
+        if let Err(error) = written {
+            panic!("Could not write the crafted library: {}", error);
+        }
+        crafted
+    }
+
+    /// Reads the native-endian field of `N` bytes at `at`, where `N` is 2,
+    /// 4 or 8
+    fn field<const N: usize>(data: &[u8], at: usize) -> usize {
+        let mut bytes = [0; 8];
+        bytes[..N].copy_from_slice(&data[at..at + N]);
+        match N {
+            2 => usize::from(u16::from_ne_bytes([bytes[0], bytes[1]])),
+            4 => u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+            _ => u64::from_ne_bytes(bytes) as usize,
+        }
+    }
+
+    /// The offsets of the section headers of a 64-bit object
+    fn section_headers(data: &[u8]) -> Vec<usize> {
+        let (shoff, shentsize, shnum) = (
+            field::<8>(data, 40),
+            field::<2>(data, 58),
+            field::<2>(data, 60),
+        );
+        (0..shnum).map(|i| shoff + i * shentsize).collect()
+    }
+
+    #[test]
+    fn test_symbol_table_offset_overflow() {
+        let library = test_support::build_library(
+            "overflow_strings",
+            "int exported(void) { return 1; }",
+            &[],
+        );
+        // The string table of `.dynsym` moved to the end of the address
+        // space, where adding the offset of a name overflows
+        let crafted = crafted(&library, "overflow_strings_crafted", |data| {
+            let headers = section_headers(data);
+            let Some(&dynsym) = headers
+                .iter()
+                .find(|at| field::<4>(data, *at + 4) == SHT_DYNSYM as usize)
+            else {
+                panic!("The fixture has no dynamic symbol table");
+            };
+            let strings = headers[field::<4>(data, dynsym + 40)] + 24;
+            data[strings..strings + 8].copy_from_slice(&u64::MAX.to_ne_bytes());
+        });
+        match exported_symbols_file(&crafted) {
+            Err(Error::MalformedElf { .. }) => {}
+            Err(error) => panic!("Expected a malformed ELF error, got: {}", error),
+            Ok(exports) => panic!("Read exports past the end of the file: {:?}", exports),
+        }
+    }
+
+    #[test]
+    fn test_dynamic_string_offset_overflow() {
+        let library = test_support::build_library(
+            "overflow_segments",
+            "int loadable(void) { return 1; }",
+            &["-Wl,-rpath,/opt/plugins"],
+        );
+        // Every loadable segment claiming to span the rest of the address
+        // space, past its end
+        let crafted = crafted(&library, "overflow_segments_crafted", |data| {
+            let (phoff, phentsize, phnum) = (
+                field::<8>(data, 32),
+                field::<2>(data, 54),
+                field::<2>(data, 56),
+            );
+            for at in (0..phnum).map(|i| phoff + i * phentsize) {
+                if field::<4>(data, at) == PT_LOAD as usize {
+                    data[at + 16..at + 24].copy_from_slice(&1_u64.to_ne_bytes());
+                    data[at + 32..at + 40].copy_from_slice(&u64::MAX.to_ne_bytes());
+                }
+            }
+        });
+        match runpath(&crafted) {
+            Ok(_) | Err(Error::MalformedElf { .. }) => {}
+            Err(error) => panic!("Expected a malformed ELF error, got: {}", error),
+        }
+    }
+
+    #[test]
+    fn test_runpath_of_non_elf_file() {
+        match runpath(Path::new(file!())) {
+            Err(Error::MalformedElf { .. }) => {}
+            Err(error) => panic!("Expected a malformed ELF error, got: {}", error),
+            Ok(dirs) => panic!("Read a runpath from a source file: {:?}", dirs),
+        }
+    }
+}
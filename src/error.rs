@@ -0,0 +1,245 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Errors reported by [`DynamicLibrary`](crate::DynamicLibrary)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The platform loader reported an error, carrying its message and, when
+    /// the failing call left one, the OS error number (`errno`)
+    Loader {
+        message: String,
+        raw_os_error: Option<i32>,
+    },
+    /// The handle was explicitly closed with
+    /// [`DynamicLibrary::close`](crate::DynamicLibrary::close)
+    HandleClosed,
+    /// The library was opened with global scope where local scope was
+    /// required, see
+    /// [`DynamicLibrary::require_local`](crate::DynamicLibrary::require_local)
+    NotLocal,
+    /// The symbol is not defined by the library, named by its
+    /// [`DynamicLibrary::path`](crate::DynamicLibrary::path) when known
+    SymbolMissing {
+        name: String,
+        library: Option<PathBuf>,
+    },
+    /// The symbol was resolved as a function but its value is null, as
+    /// absolute or weak undefined exports may be
+    NullSymbol { name: String },
+    /// The symbol name contains a NUL byte at `position`, so it cannot be
+    /// passed to the loader
+    InvalidSymbolName { name: String, position: usize },
+    /// The library path contains a NUL byte at `position`, so it cannot be
+    /// passed to the loader
+    InvalidCString { path: PathBuf, position: usize },
+    /// The symbol resolved to a definition outside of the objects the handle
+    /// is scoped to, such as a library symbol looked up through a
+    /// [`ProcessScope::MainOnly`](crate::ProcessScope::MainOnly) handle
+    OutsideScope(String),
+    /// The operation is not available on this platform
+    Unsupported(&'static str),
+    /// None of the names given to
+    /// [`DynamicLibrary::symbol_any`](crate::DynamicLibrary::symbol_any),
+    /// the handles given to
+    /// [`DynamicLibrary::symbol_in_chain`](crate::DynamicLibrary::symbol_in_chain)
+    /// or the paths given to
+    /// [`DynamicLibrary::open_first`](crate::DynamicLibrary::open_first)
+    /// resolved. Holds the failure for each candidate, in order
+    NoCandidateResolved(Vec<Error>),
+    /// A function signature cannot describe a C function
+    InvalidSignature { name: String, reason: String },
+    /// The version string exported by a plugin is not a semantic version
+    InvalidVersion { name: String, reason: String },
+    /// The version exported by a plugin does not satisfy the required one
+    VersionMismatch {
+        name: String,
+        found: String,
+        required: String,
+    },
+    /// A type does not have the size of the pointers it is meant to hold, in
+    /// bytes
+    LayoutMismatch { expected: usize, found: usize },
+    /// Reading a file from disk failed
+    Io {
+        path: PathBuf,
+        kind: io::ErrorKind,
+        message: String,
+        raw_os_error: Option<i32>,
+    },
+    /// A file opened as a bundle of libraries is not well-formed, see
+    /// [`pack_bundle`](crate::pack_bundle)
+    MalformedBundle { path: PathBuf, reason: String },
+    /// A file inspected on disk is not a well-formed ELF object
+    MalformedElf { path: PathBuf, reason: String },
+    /// The loader rejected the file opened as a library because it is
+    /// truncated or not a shared object at all
+    InvalidLibraryFile { path: PathBuf, reason: String },
+    /// A library was to be opened by a relative path or a bare file name
+    /// while only absolute paths are accepted, see
+    /// [`set_strict_paths`](crate::set_strict_paths)
+    RelativePathRejected(PathBuf),
+    /// A library file, or the directory holding it, could be replaced by
+    /// another user, see
+    /// [`DynamicLibrary::open_secure`](crate::DynamicLibrary::open_secure)
+    InsecurePermissions { path: PathBuf, reason: String },
+    /// The plugin descriptor read by
+    /// [`DynamicLibrary::load_plugin_descriptor`](crate::DynamicLibrary::load_plugin_descriptor)
+    /// does not start with
+    /// [`PLUGIN_DESCRIPTOR_MAGIC`](crate::PLUGIN_DESCRIPTOR_MAGIC), holding
+    /// what it starts with instead
+    DescriptorMagicMismatch { found: u64 },
+    /// A spec string given to
+    /// [`DynamicLibrary::open_spec`](crate::DynamicLibrary::open_spec) names
+    /// an unknown flag, `token`
+    InvalidOpenSpec { spec: String, token: String },
+    /// The operation did not complete within the given time, see
+    /// [`DynamicLibrary::open_with_timeout`](crate::DynamicLibrary::open_with_timeout)
+    Timeout(Duration),
+}
+
+impl Error {
+    pub(crate) fn loader(message: impl Into<String>) -> Error {
+        Error::Loader {
+            message: message.into(),
+            raw_os_error: None,
+        }
+    }
+
+    /// Returns the OS error number behind this error, if any: the `errno`
+    /// the loader left for [`Error::Loader`], or the code of a failed file
+    /// access. Captured on a best-effort basis, as the loader does not
+    /// document which calls set it
+    pub const fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            Error::Loader { raw_os_error, .. } | Error::Io { raw_os_error, .. } => *raw_os_error,
+            _ => None,
+        }
+    }
+
+    pub(crate) fn io(path: &Path, err: &io::Error) -> Error {
+        Error::Io {
+            path: path.to_path_buf(),
+            kind: err.kind(),
+            message: err.to_string(),
+            raw_os_error: err.raw_os_error(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Loader { message, .. } => f.write_str(message),
+            Error::HandleClosed => f.write_str("the library handle was closed"),
+            Error::NotLocal => f.write_str("the library was opened with global scope"),
+            Error::SymbolMissing {
+                name,
+                library: Some(library),
+            } => write!(f, "undefined symbol: {name} in {}", library.display()),
+            Error::SymbolMissing {
+                name,
+                library: None,
+            } => write!(f, "undefined symbol: {name}"),
+            Error::NullSymbol { name } => write!(f, "`{name}` resolves to a null address"),
+            Error::InvalidSymbolName { name, position } => write!(
+                f,
+                "invalid symbol name `{}`: NUL byte at position {position}",
+                name.escape_debug()
+            ),
+            Error::InvalidCString { path, position } => write!(
+                f,
+                "invalid library path `{}`: NUL byte at position {position}",
+                path.display()
+            ),
+            Error::OutsideScope(name) => {
+                write!(f, "`{name}` is defined outside of the handle's scope")
+            }
+            Error::Unsupported(what) => write!(f, "{what} is not supported on this platform"),
+            Error::NoCandidateResolved(failures) => {
+                f.write_str("no candidate resolved")?;
+                for (i, failure) in failures.iter().enumerate() {
+                    let separator = if i == 0 { ": " } else { "; " };
+                    write!(f, "{separator}{failure}")?;
+                }
+                Ok(())
+            }
+            Error::InvalidSignature { name, reason } => {
+                write!(f, "invalid signature for `{name}`: {reason}")
+            }
+            Error::InvalidVersion { name, reason } => {
+                write!(f, "invalid version in `{name}`: {reason}")
+            }
+            Error::VersionMismatch {
+                name,
+                found,
+                required,
+            } => write!(f, "`{name}` is version {found}, {required} is required"),
+            Error::LayoutMismatch { expected, found } => write!(
+                f,
+                "expected a pointer table of {expected} bytes, found {found} bytes"
+            ),
+            Error::Io { path, message, .. } => write!(f, "{}: {message}", path.display()),
+            Error::MalformedBundle { path, reason } => {
+                write!(f, "{}: malformed bundle: {reason}", path.display())
+            }
+            Error::MalformedElf { path, reason } => {
+                write!(f, "{}: malformed ELF object: {reason}", path.display())
+            }
+            Error::InvalidLibraryFile { path, reason } => {
+                write!(f, "{}: not a valid library file: {reason}", path.display())
+            }
+            Error::RelativePathRejected(path) => {
+                write!(
+                    f,
+                    "{}: only absolute library paths are allowed",
+                    path.display()
+                )
+            }
+            Error::InsecurePermissions { path, reason } => {
+                write!(f, "{}: insecure permissions: {reason}", path.display())
+            }
+            Error::DescriptorMagicMismatch { found } => write!(
+                f,
+                "plugin descriptor starts with {found:#x} instead of the magic {:#x}",
+                crate::PLUGIN_DESCRIPTOR_MAGIC
+            ),
+            Error::InvalidOpenSpec { spec, token } => {
+                write!(f, "invalid open spec `{spec}`: unknown flag `{token}`")
+            }
+            Error::Timeout(timeout) => write!(f, "timed out after {timeout:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Keeps the message and the error itself, as the source, and picks the
+/// kind from the OS error number when there is one, so a missing or
+/// unreadable library maps to `NotFound` or `PermissionDenied`
+impl From<Error> for io::Error {
+    fn from(error: Error) -> io::Error {
+        let kind = match &error {
+            Error::Io { kind, .. } => *kind,
+            Error::Loader {
+                raw_os_error: Some(code),
+                ..
+            } => io::Error::from_raw_os_error(*code).kind(),
+            Error::InvalidLibraryFile { .. }
+            | Error::MalformedBundle { .. }
+            | Error::MalformedElf { .. }
+            | Error::DescriptorMagicMismatch { .. }
+            | Error::NullSymbol { .. } => io::ErrorKind::InvalidData,
+            Error::Timeout(_) => io::ErrorKind::TimedOut,
+            Error::RelativePathRejected(_)
+            | Error::InvalidOpenSpec { .. }
+            | Error::InvalidCString { .. } => io::ErrorKind::InvalidInput,
+            Error::InsecurePermissions { .. } => io::ErrorKind::PermissionDenied,
+            Error::Unsupported(_) => io::ErrorKind::Unsupported,
+            _ => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, error)
+    }
+}
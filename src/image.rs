@@ -0,0 +1,963 @@
+//! Access to the in-memory image of a loaded library through the loader's
+//! link map, for the details `dlsym` does not report
+
+#[cfg(feature = "regex")]
+use std::collections::HashMap;
+use std::{
+    ffi::{CStr, CString, OsStr, c_char},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    ptr, slice,
+};
+
+use crate::{
+    DynamicLibrary, Error, FnPtr, dl,
+    elf::{ElfFile, ElfSymbol},
+};
+
+const DT_NULL: isize = 0;
+const DT_HASH: isize = 4;
+const DT_STRTAB: isize = 5;
+const DT_SYMTAB: isize = 6;
+const DT_GNU_HASH: isize = 0x6fff_fef5;
+
+const STB_LOCAL: u8 = 0;
+const STB_WEAK: u8 = 2;
+
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_TLS: u8 = 6;
+const STT_GNU_IFUNC: u8 = 10;
+
+const STV_INTERNAL: u8 = 1;
+const STV_HIDDEN: u8 = 2;
+const STV_PROTECTED: u8 = 3;
+
+#[cfg(target_pointer_width = "64")]
+type Sym = libc::Elf64_Sym;
+#[cfg(target_pointer_width = "32")]
+type Sym = libc::Elf32_Sym;
+
+/// The public prefix of glibc's `struct link_map`
+#[repr(C)]
+struct LinkMap {
+    l_addr: usize,
+    l_name: *const c_char,
+    l_ld: *const Dyn,
+    l_next: *mut LinkMap,
+    l_prev: *mut LinkMap,
+}
+
+/// `ElfW(Dyn)`
+#[repr(C)]
+struct Dyn {
+    d_tag: isize,
+    d_val: usize,
+}
+
+/// What kind of entity a symbol names, see [`DynamicLibrary::symbol_type`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolType {
+    /// Code: `STT_FUNC`, or `STT_GNU_IFUNC` whose resolver returns code
+    Function,
+    /// Data: `STT_OBJECT`
+    Object,
+    /// Anything else, such as thread-local or untyped symbols
+    Other,
+}
+
+/// Whether, and how, other objects may bind to a symbol, see
+/// [`DynamicLibrary::symbol_visibility`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    /// Exported and preemptible: `STV_DEFAULT`
+    Default,
+    /// Not exported, so `dlsym` cannot find it: `STV_HIDDEN`
+    Hidden,
+    /// Exported, but the library's own references always bind to its own
+    /// definition: `STV_PROTECTED`
+    Protected,
+    /// Hidden, and never called from outside the library even through a
+    /// pointer: `STV_INTERNAL`
+    Internal,
+}
+
+impl DynamicLibrary {
+    /// Reports whether the exported symbol `name` is a function or data,
+    /// read from the dynamic symbol table of the loaded image. Useful before
+    /// transmuting the address returned by [`DynamicLibrary::symbol`]
+    pub fn symbol_type(&self, name: &str) -> Result<SymbolType, Error> {
+        let image = self.mapped_image()?;
+        let symbol = image
+            .dynamic_symbols()
+            .into_iter()
+            .find(|symbol| symbol.is_defined() && symbol.name == name)
+            .ok_or_else(|| self.symbol_missing(name))?;
+        Ok(match symbol.kind() {
+            STT_FUNC | STT_GNU_IFUNC => SymbolType::Function,
+            STT_OBJECT => SymbolType::Object,
+            _ => SymbolType::Other,
+        })
+    }
+
+    /// Returns the first export of the library, in name order, whose name
+    /// satisfies `predicate`, along with its address. Lets callers match
+    /// names by pattern or demangle them without the crate doing either
+    pub fn find_symbol(
+        &self,
+        predicate: impl Fn(&str) -> bool,
+    ) -> Result<Option<(String, *mut u8)>, Error> {
+        match self.matching_exports(predicate)?.into_iter().next() {
+            Some(name) => {
+                let address = unsafe { self.symbol::<u8>(&name)? };
+                Ok(Some((name, address)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Resolves every export of the library whose name matches `pattern`,
+    /// for dispatch tables built from naming conventions such as `^op_`
+    #[cfg(feature = "regex")]
+    pub fn resolve_matching(
+        &self,
+        pattern: &regex::Regex,
+    ) -> Result<HashMap<String, *mut u8>, Error> {
+        self.matching_exports(|name| pattern.is_match(name))?
+            .into_iter()
+            .map(|name| {
+                let address = unsafe { self.symbol::<u8>(&name)? };
+                Ok((name, address))
+            })
+            .collect()
+    }
+
+    /// The names of the exports satisfying `predicate`, sorted and without
+    /// the duplicates symbol versioning leaves
+    pub(crate) fn matching_exports(
+        &self,
+        predicate: impl Fn(&str) -> bool,
+    ) -> Result<Vec<String>, Error> {
+        let mut names: Vec<String> = self
+            .mapped_image()?
+            .dynamic_symbols()
+            .into_iter()
+            .filter(|symbol| symbol.is_export() && predicate(&symbol.name))
+            .map(|symbol| symbol.name)
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Reports whether the library at `new_path` can replace this one
+    /// without breaking code bound to it: it must export at least every
+    /// symbol this library exports, so each pointer can be resolved again
+    /// after the swap. The exports of the replacement are read from the
+    /// file rather than loaded, so its initializers do not run and a new
+    /// build written over the path this library was loaded from is checked
+    /// rather than the loaded image. Only names are compared, not types
+    pub fn compatible_swap(&self, new_path: &Path) -> Result<bool, Error> {
+        let exports = self.matching_exports(|_| true)?;
+        let replacements = crate::exported_symbols_file(new_path)?;
+        Ok(exports.iter().all(|name| replacements.contains(name)))
+    }
+
+    /// Reports the ELF visibility of the symbol `name` defined by the
+    /// library, to diagnose why `dlsym` cannot find a symbol it defines.
+    ///
+    /// The linker leaves hidden and internal symbols out of the dynamic
+    /// symbol table and turns them into local ones, so a symbol only found
+    /// as local in the full symbol table of the loaded file, including a
+    /// `static` one, is reported as [`Visibility::Hidden`]. Stripped
+    /// libraries have no such table
+    pub fn symbol_visibility(&self, name: &str) -> Result<Visibility, Error> {
+        let image = self.mapped_image()?;
+        let named = |symbol: &ElfSymbol| symbol.is_defined() && symbol.name == name;
+        let visibility = match image.dynamic_symbols().into_iter().find(named) {
+            Some(symbol) => symbol.visibility(),
+            None => {
+                let symbol = match self.loaded_path()? {
+                    Some(path) => ElfFile::read(&path)?.symbols()?.into_iter().find(named),
+                    None => None,
+                };
+                match symbol {
+                    Some(symbol) if symbol.binding() == STB_LOCAL => STV_HIDDEN,
+                    Some(symbol) => symbol.visibility(),
+                    None => {
+                        return Err(self.symbol_missing(name));
+                    }
+                }
+            }
+        };
+        Ok(match visibility {
+            STV_INTERNAL => Visibility::Internal,
+            STV_HIDDEN => Visibility::Hidden,
+            STV_PROTECTED => Visibility::Protected,
+            _ => Visibility::Default,
+        })
+    }
+
+    /// Resolves `name` as the pointer-sized `F`, usually a function pointer,
+    /// after checking that every undefined reference of the library can be
+    /// bound, so that calling it will not abort on a lazy binding failure.
+    ///
+    /// `dlsym` returns the address of the function itself, which exists even
+    /// when the symbols it calls do not, and the ELF format does not record
+    /// which function makes which reference. So the check covers the whole
+    /// library: a reference the function never makes still fails it, and a
+    /// symbol loaded into the global scope after the check may still change
+    /// what a reference binds to. Weak references are allowed to stay
+    /// unresolved, as the loader allows. An unresolved reference fails with
+    /// [`Error::SymbolMissing`] naming it, and a null `name` with
+    /// [`Error::NullSymbol`]
+    ///
+    /// # Safety
+    ///
+    /// `F` must match the actual type of the symbol
+    pub unsafe fn symbol_validated<F: FnPtr>(&self, name: &str) -> Result<F, Error> {
+        let symbol = unsafe { self.get_fn::<F>(name)? };
+        let unresolved = self
+            .mapped_image()?
+            .dynamic_symbols()
+            .into_iter()
+            .find(|symbol| {
+                !symbol.is_defined()
+                    && !symbol.name.is_empty()
+                    && symbol.binding() != STB_WEAK
+                    && !self.resolves(&symbol.name)
+            });
+        match unresolved {
+            Some(reference) => Err(self.symbol_missing(&reference.name)),
+            None => Ok(symbol),
+        }
+    }
+
+    /// Returns the address of the definition of `name` in this very
+    /// library, read from its dynamic symbol table rather than asked of the
+    /// loader, so no other object can supply it.
+    ///
+    /// Under a sanitizer, see [`DynamicLibrary::running_under_sanitizer`],
+    /// the runtime is loaded first and defines `malloc`, `memcpy` and many
+    /// other libc functions, so references made by libraries and lookups in
+    /// the global scope, such as through the handle of
+    /// `DynamicLibrary::open(None)`, get the sanitizer's wrappers. This gets
+    /// the library's own definition regardless, e.g. the real `malloc` from
+    /// a handle to libc. Indirect functions (`STT_GNU_IFUNC`) and
+    /// thread-local variables are rejected with [`Error::Unsupported`], as
+    /// only the loader can compute their address
+    ///
+    /// # Safety
+    ///
+    /// Calling a definition the sanitizer wraps bypasses its bookkeeping,
+    /// which may lead to false reports or crashes if memory is shared with
+    /// instrumented code
+    pub unsafe fn symbol_uninterposed<T>(&self, name: &str) -> Result<*mut T, Error> {
+        let image = self.mapped_image()?;
+        let symbol = image
+            .dynamic_symbols()
+            .into_iter()
+            .find(|symbol| symbol.is_export() && symbol.name == name)
+            .ok_or_else(|| self.symbol_missing(name))?;
+        match symbol.kind() {
+            STT_GNU_IFUNC => Err(Error::Unsupported(
+                "resolving an indirect function without the loader",
+            )),
+            STT_TLS => Err(Error::Unsupported(
+                "resolving a thread-local variable without the loader",
+            )),
+            _ => Ok(image.base.wrapping_add(symbol.value as usize) as *mut T),
+        }
+    }
+
+    /// Whether the loader can bind a reference to `name` from this library,
+    /// looking in the library and its dependencies, then the global scope
+    fn resolves(&self, name: &str) -> bool {
+        let Ok(name) = CString::new(name) else {
+            return false;
+        };
+        [self.handle.as_ptr(), libc::RTLD_DEFAULT.cast()]
+            .into_iter()
+            .any(|handle| {
+                dl::check_for_errors_in(|| unsafe { dl::symbol(handle, name.as_ptr()) }).is_ok()
+            })
+    }
+
+    /// The path the loader mapped the library from, `None` for the main
+    /// program
+    fn loaded_path(&self) -> Result<Option<PathBuf>, Error> {
+        if self.closed {
+            return Err(Error::HandleClosed);
+        }
+        let map = dl::link_map(self.handle.as_ptr())?.cast::<LinkMap>();
+        let name = unsafe { (*map).l_name };
+        if name.is_null() {
+            return Ok(None);
+        }
+        let name = unsafe { CStr::from_ptr(name) };
+        Ok((!name.is_empty()).then(|| Path::new(OsStr::from_bytes(name.to_bytes())).to_path_buf()))
+    }
+
+    /// Runs `f` while holding an extra loader reference to the library, so
+    /// its image stays mapped for the duration of the call even if every
+    /// other reference is released meanwhile, by another thread or by C code
+    /// calling `dlclose` on the same object
+    ///
+    /// # Panics
+    ///
+    /// If the handle was closed or the loader refuses the extra reference
+    pub fn with_pinned<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _pin = match self.pin() {
+            Err(err) => panic!("failed to pin the library: {err}"),
+            Ok(pin) => pin,
+        };
+        f()
+    }
+
+    /// Takes a new reference to the already loaded object by opening its
+    /// link map name again with `RTLD_NOLOAD`
+    fn pin(&self) -> Result<Pin, Error> {
+        if self.closed {
+            return Err(Error::HandleClosed);
+        }
+        let map = dl::link_map(self.handle.as_ptr())?.cast::<LinkMap>();
+        let name = unsafe { (*map).l_name };
+        // The main program has an empty name and is never unloaded
+        let name = (!name.is_null())
+            .then(|| unsafe { CStr::from_ptr(name) })
+            .filter(|name| !name.is_empty());
+        let handle = dl::open_cstr(name, dl::LAZY | libc::RTLD_NOLOAD)?;
+        if handle.is_null() {
+            return Err(Error::loader("the library is no longer loaded"));
+        }
+        Ok(Pin(handle))
+    }
+
+    /// Returns the directories the loader searches, in order, for the
+    /// dependencies of this library, such as the search path the process
+    /// started with, the library's runpath and the system directories
+    pub fn handle_search_path(&self) -> Result<Vec<PathBuf>, Error> {
+        if self.closed {
+            return Err(Error::HandleClosed);
+        }
+        dl::search_dirs(self.handle.as_ptr())
+    }
+
+    /// Asks the kernel to read the loaded segments of the library ahead
+    /// (`MADV_WILLNEED`) and touches each of their pages, so the first calls
+    /// into it do not wait on page faults. A best-effort performance aid:
+    /// pages may still be evicted later under memory pressure
+    pub fn prefault(&self) -> Result<(), Error> {
+        let segments: Vec<(usize, usize)> = self
+            .segments()?
+            .into_iter()
+            .filter(|segment| segment.kind == libc::PT_LOAD && segment.flags & libc::PF_R != 0)
+            .map(|segment| (segment.address, segment.mem_size))
+            .collect();
+        if segments.is_empty() {
+            return Err(Error::loader("failed to find the segments of the library"));
+        }
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as usize;
+        for (start, len) in segments {
+            let first = start - start % page;
+            let end = start + len;
+            unsafe {
+                libc::madvise(first as *mut libc::c_void, end - first, libc::MADV_WILLNEED);
+            }
+            for address in (first..end).step_by(page) {
+                unsafe { ptr::read_volatile(address.max(start) as *const u8) };
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the initial value of the thread-local variable `name`
+    /// defined by the library: the bytes the loader copies into the
+    /// variable's slot in the TLS block of each new thread, read from the
+    /// initialization image of the library's `PT_TLS` segment, with the
+    /// zero-initialized tail (`.tbss`) filled in.
+    ///
+    /// An advanced, Linux and ELF-specific tool for plugins that ship
+    /// thread-local defaults. It reports the template, not the value the
+    /// variable currently has in any thread
+    pub fn tls_template(&self, name: &str) -> Result<Vec<u8>, Error> {
+        let symbol = self
+            .mapped_image()?
+            .dynamic_symbols()
+            .into_iter()
+            .find(|symbol| symbol.is_export() && symbol.name == name)
+            .ok_or_else(|| self.symbol_missing(name))?;
+        if symbol.kind() != STT_TLS {
+            return Err(Error::loader(format!(
+                "`{name}` is not a thread-local variable"
+            )));
+        }
+        let Some(tls) = self
+            .segments()?
+            .into_iter()
+            .find(|segment| segment.kind == libc::PT_TLS)
+        else {
+            return Err(Error::loader("the library has no TLS segment"));
+        };
+        let (start, size) = (symbol.value as usize, symbol.size as usize);
+        if start.saturating_add(size) > tls.mem_size {
+            return Err(Error::loader(format!(
+                "`{name}` lies outside of the TLS segment"
+            )));
+        }
+        let initialized = tls.file_size.min(start + size).saturating_sub(start);
+        let mut template = vec![0; size];
+        if initialized > 0 {
+            let image =
+                unsafe { slice::from_raw_parts((tls.address + start) as *const u8, initialized) };
+            template[..initialized].copy_from_slice(image);
+        }
+        Ok(template)
+    }
+
+    /// The program headers of the library, located in memory
+    pub(crate) fn segments(&self) -> Result<Vec<Segment>, Error> {
+        let image = self.mapped_image()?;
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut query = (image.dynamic as usize, &mut segments);
+        unsafe {
+            libc::dl_iterate_phdr(
+                Some(object_segments),
+                (&mut query as *mut (usize, &mut Vec<Segment>)).cast(),
+            );
+        }
+        Ok(segments)
+    }
+
+    pub(crate) fn mapped_image(&self) -> Result<MappedImage, Error> {
+        if self.closed {
+            return Err(Error::HandleClosed);
+        }
+        let map = dl::link_map(self.handle.as_ptr())?;
+        Ok(unsafe { MappedImage::from_link_map(map.cast()) })
+    }
+}
+
+/// A program header of a loaded object, with its address in memory
+pub(crate) struct Segment {
+    pub(crate) kind: u32,
+    flags: u32,
+    pub(crate) address: usize,
+    file_size: usize,
+    pub(crate) mem_size: usize,
+}
+
+/// `dl_iterate_phdr` callback collecting the segments of the object whose
+/// dynamic section is at the address given in `query`
+unsafe extern "C" fn object_segments(
+    info: *mut libc::dl_phdr_info,
+    _size: libc::size_t,
+    query: *mut libc::c_void,
+) -> libc::c_int {
+    unsafe {
+        let (dynamic, segments) = &mut *query.cast::<(usize, &mut Vec<Segment>)>();
+        let info = &*info;
+        let headers = (0..info.dlpi_phnum as usize).map(|i| &*info.dlpi_phdr.add(i));
+        let is_match = headers.clone().any(|header| {
+            header.p_type == libc::PT_DYNAMIC
+                && info.dlpi_addr as usize + header.p_vaddr as usize == *dynamic
+        });
+        if !is_match {
+            return 0;
+        }
+        segments.extend(headers.map(|header| Segment {
+            kind: header.p_type,
+            flags: header.p_flags,
+            address: info.dlpi_addr as usize + header.p_vaddr as usize,
+            file_size: header.p_filesz as usize,
+            mem_size: header.p_memsz as usize,
+        }));
+        1
+    }
+}
+
+/// An extra reference to a loaded object, released on drop. A failure to
+/// release it is handled as the [`crate::DropErrorPolicy`] in effect says
+struct Pin(*mut u8);
+
+impl Drop for Pin {
+    fn drop(&mut self) {
+        match dl::check_for_errors_in(|| unsafe { dl::close(self.0) }) {
+            Ok(()) => crate::init::forget_unloaded(self.0),
+            Err(error) => crate::report_drop_error(&error),
+        }
+    }
+}
+
+/// A loaded object as described by its link map entry
+pub(crate) struct MappedImage {
+    /// Difference between the addresses in the ELF file and in memory
+    pub(crate) base: usize,
+    dynamic: *const Dyn,
+}
+
+impl MappedImage {
+    /// # Safety
+    ///
+    /// `map` must be a live entry of the loader's link map
+    const unsafe fn from_link_map(map: *const LinkMap) -> MappedImage {
+        unsafe {
+            MappedImage {
+                base: (*map).l_addr,
+                dynamic: (*map).l_ld,
+            }
+        }
+    }
+
+    /// Returns the value of the first dynamic entry tagged `tag`
+    const fn dynamic_entry(&self, tag: isize) -> Option<usize> {
+        if self.dynamic.is_null() {
+            return None;
+        }
+        let mut entry = self.dynamic;
+        unsafe {
+            while (*entry).d_tag != DT_NULL {
+                if (*entry).d_tag == tag {
+                    return Some((*entry).d_val);
+                }
+                entry = entry.add(1);
+            }
+        }
+        None
+    }
+
+    /// Returns the in-memory address a dynamic entry points to. glibc
+    /// relocates these entries in place on most architectures but not all,
+    /// so a value below the load base is taken to be unrelocated
+    fn dynamic_address(&self, tag: isize) -> Option<usize> {
+        self.dynamic_entry(tag).map(|value| {
+            if value < self.base {
+                value + self.base
+            } else {
+                value
+            }
+        })
+    }
+
+    /// Number of entries of the dynamic symbol table, which the ELF format
+    /// only records in the hash tables
+    fn dynamic_symbol_count(&self) -> usize {
+        unsafe {
+            if let Some(hash) = self.dynamic_address(DT_HASH) {
+                // nbucket, nchain: every symbol has a chain entry
+                return *(hash as *const u32).add(1) as usize;
+            }
+            let Some(gnu_hash) = self.dynamic_address(DT_GNU_HASH) else {
+                return 0;
+            };
+            let header = gnu_hash as *const u32;
+            let nbuckets = *header as usize;
+            let symoffset = *header.add(1) as usize;
+            let bloom_size = *header.add(2) as usize;
+            let buckets = header.add(4).cast::<usize>().add(bloom_size).cast::<u32>();
+            let chains = buckets.add(nbuckets);
+            let last_bucket = (0..nbuckets)
+                .map(|i| *buckets.add(i) as usize)
+                .max()
+                .unwrap_or(0);
+            if last_bucket < symoffset {
+                return symoffset;
+            }
+            // The chain of the last bucket ends at the last symbol, marked by
+            // the low bit of its hash
+            let mut index = last_bucket;
+            while *chains.add(index - symoffset) & 1 == 0 {
+                index += 1;
+            }
+            index + 1
+        }
+    }
+
+    /// Returns the entries of the dynamic symbol table as mapped in memory
+    pub(crate) fn dynamic_symbols(&self) -> Vec<ElfSymbol> {
+        let (Some(symtab), Some(strtab)) = (
+            self.dynamic_address(DT_SYMTAB),
+            self.dynamic_address(DT_STRTAB),
+        ) else {
+            return Vec::new();
+        };
+        let symtab = symtab as *const Sym;
+        let strtab = strtab as *const c_char;
+        (0..self.dynamic_symbol_count())
+            .map(|i| unsafe {
+                let sym = &*symtab.add(i);
+                let name = CStr::from_ptr(strtab.add(sym.st_name as usize));
+                ElfSymbol {
+                    name: name.to_string_lossy().into_owned(),
+                    value: sym.st_value,
+                    size: sym.st_size,
+                    info: sym.st_info,
+                    other: sym.st_other,
+                    shndx: sym.st_shndx,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{ffi::CString, mem, path::Path, sync::mpsc, thread};
+
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn test_symbol_type() {
+        let path = test_support::build_library(
+            "symbol_type",
+            "int exported_counter = 3;\n\
+             int exported_function(void) { return exported_counter; }\n",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(
+            lib.symbol_type("exported_function"),
+            Ok(SymbolType::Function)
+        );
+        assert_eq!(lib.symbol_type("exported_counter"), Ok(SymbolType::Object));
+        assert_eq!(
+            lib.symbol_type("not_exported"),
+            Err(Error::SymbolMissing {
+                name: "not_exported".to_string(),
+                library: Some(path),
+            })
+        );
+
+        let libm = match DynamicLibrary::open(Some(Path::new("libm.so.6"))) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(libm) => libm,
+        };
+        assert_eq!(libm.symbol_type("cos"), Ok(SymbolType::Function));
+    }
+
+    #[test]
+    fn test_find_symbol() {
+        let path = test_support::build_library(
+            "find_symbol",
+            "double tan(double x) { return x; }\n\
+             double cos(double x) { return 1.0; }\n\
+             double sin(double x) { return 0.0; }\n",
+            &["-fno-builtin"],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        match lib.find_symbol(|name| name.starts_with("co")) {
+            Err(error) => panic!("Could not search the exports: {}", error),
+            Ok(None) => panic!("No export starts with `co`"),
+            Ok(Some((name, address))) => {
+                assert_eq!(name, "cos");
+                assert_eq!(unsafe { lib.symbol::<u8>("cos") }, Ok(address));
+            }
+        }
+        assert_eq!(lib.find_symbol(|name| name.starts_with("not_")), Ok(None));
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn test_resolve_matching() {
+        let path = test_support::build_library(
+            "resolve_matching",
+            "int op_add(int a, int b) { return a + b; }\n\
+             int op_sub(int a, int b) { return a - b; }\n\
+             int other(void) { return 0; }\n",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        let Ok(pattern) = regex::Regex::new("^op_") else {
+            panic!("Could not compile the pattern");
+        };
+        let table = match lib.resolve_matching(&pattern) {
+            Err(error) => panic!("Could not resolve the matching exports: {}", error),
+            Ok(table) => table,
+        };
+        let mut names: Vec<&str> = table.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        assert_eq!(names, ["op_add", "op_sub"]);
+        assert_eq!(
+            table.get("op_add").copied(),
+            unsafe { lib.symbol::<u8>("op_add") }.ok()
+        );
+    }
+
+    #[test]
+    fn test_compatible_swap() {
+        let old = test_support::build_library(
+            "swap_old",
+            "int plugin_start(void) { return 1; }\n\
+             int plugin_stop(void) { return 1; }\n",
+            &[],
+        );
+        let compatible = test_support::build_library(
+            "swap_compatible",
+            "int plugin_start(void) { return 2; }\n\
+             int plugin_stop(void) { return 2; }\n\
+             int plugin_pause(void) { return 2; }\n",
+            &[],
+        );
+        let incompatible = test_support::build_library(
+            "swap_incompatible",
+            "int plugin_start(void) { return 3; }\n",
+            &[],
+        );
+        let old = match DynamicLibrary::open(Some(&old)) {
+            Err(error) => panic!("Could not open the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(old.compatible_swap(&compatible), Ok(true));
+        assert_eq!(old.compatible_swap(&incompatible), Ok(false));
+        assert!(
+            old.compatible_swap(Path::new("/does/not/exist.so"))
+                .is_err()
+        );
+
+        let source = "int plugin_start(void) { return 1; }\n";
+        let path = test_support::build_library(
+            "swap_overwritten",
+            &format!("{source}int plugin_stop(void) {{ return 1; }}\n"),
+            &[],
+        );
+        let loaded = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not open the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        // Replaces the file at the same path, leaving the loaded one intact
+        test_support::build_library("swap_overwritten", source, &[]);
+        assert_eq!(loaded.compatible_swap(&path), Ok(false));
+    }
+
+    #[test]
+    fn test_symbol_visibility() {
+        let path = test_support::build_library(
+            "symbol_visibility",
+            "__attribute__((visibility(\"hidden\"))) int hidden_helper(void) { return 1; }\n\
+             __attribute__((visibility(\"protected\"))) int protected_entry(void) { return 2; }\n\
+             int default_entry(void) { return hidden_helper(); }\n",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        assert!(unsafe { lib.symbol::<u8>("hidden_helper") }.is_err());
+        assert_eq!(
+            lib.symbol_visibility("hidden_helper"),
+            Ok(Visibility::Hidden)
+        );
+        assert_eq!(
+            lib.symbol_visibility("protected_entry"),
+            Ok(Visibility::Protected)
+        );
+        assert_eq!(
+            lib.symbol_visibility("default_entry"),
+            Ok(Visibility::Default)
+        );
+        assert_eq!(
+            lib.symbol_visibility("not_defined"),
+            Err(Error::SymbolMissing {
+                name: "not_defined".to_string(),
+                library: Some(path),
+            })
+        );
+    }
+
+    #[test]
+    fn test_symbol_validated() {
+        let complete = test_support::build_library(
+            "validated",
+            "int callable(void) { return 7; }\n\
+             __asm__(\".globl null_callable\\n.set null_callable, 0\");\n",
+            &[],
+        );
+        let broken = test_support::build_library(
+            "validated_broken",
+            "void missing_function(void);\n\
+             int callable(void) { return 7; }\n\
+             void call_missing(void) { missing_function(); }\n",
+            &[],
+        );
+        // Lazy explicitly, as concurrent tests may force eager binding
+        // through the environment
+        let (complete, broken) = match (
+            DynamicLibrary::open(Some(&complete)),
+            DynamicLibrary::options().now(false).open(Some(&broken)),
+        ) {
+            (Ok(complete), Ok(broken)) => (complete, broken),
+            (Err(error), _) | (_, Err(error)) => panic!("Could not load the fixtures: {}", error),
+        };
+
+        match unsafe { complete.symbol_validated::<extern "C" fn() -> i32>("callable") } {
+            Err(error) => panic!("Could not validate a callable function: {}", error),
+            Ok(callable) => assert_eq!(callable(), 7),
+        }
+        assert!(unsafe { broken.symbol::<u8>("call_missing") }.is_ok());
+        match unsafe { broken.symbol_validated::<extern "C" fn()>("call_missing") } {
+            Err(Error::SymbolMissing { name, library }) => {
+                assert_eq!(name, "missing_function");
+                assert_eq!(library.as_deref(), broken.path());
+            }
+            Err(error) => panic!("Expected a missing symbol, got: {}", error),
+            Ok(_) => panic!("Validated a function calling a missing symbol."),
+        }
+        assert_eq!(
+            unsafe { complete.symbol_validated::<extern "C" fn()>("null_callable") },
+            Err(Error::NullSymbol {
+                name: "null_callable".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_symbol_uninterposed() {
+        let path = test_support::build_library(
+            "uninterposed",
+            "int uninterposed(void) { return 5; }\n\
+             __thread int thread_local_counter;\n",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        let via_loader = unsafe { lib.symbol::<u8>("uninterposed") };
+        let direct = unsafe { lib.symbol_uninterposed::<u8>("uninterposed") };
+        assert!(direct.is_ok());
+        assert_eq!(direct, via_loader);
+        assert!(matches!(
+            unsafe { lib.symbol_uninterposed::<u8>("thread_local_counter") },
+            Err(Error::Unsupported(_))
+        ));
+        assert!(unsafe { lib.symbol_uninterposed::<u8>("not_defined") }.is_err());
+
+        if !DynamicLibrary::running_under_sanitizer() {
+            return;
+        }
+        let (libc, process) = match (
+            DynamicLibrary::open(Some(Path::new("libc.so.6"))),
+            DynamicLibrary::open(None),
+        ) {
+            (Ok(libc), Ok(process)) => (libc, process),
+            (Err(error), _) | (_, Err(error)) => panic!("Could not open libc: {}", error),
+        };
+        let wrapped = unsafe { process.symbol::<u8>("malloc") };
+        match unsafe { libc.symbol_uninterposed::<u8>("malloc") } {
+            Err(error) => panic!("Could not find the real malloc: {}", error),
+            Ok(real) => assert_ne!(Ok(real), wrapped),
+        }
+    }
+
+    #[test]
+    fn test_with_pinned_outlives_competing_close() {
+        let path =
+            test_support::build_library("pinned", "int pinned_call(void) { return 42; }\n", &[]);
+        let mut lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        let pinned_call = match unsafe { lib.symbol::<u8>("pinned_call") } {
+            Err(error) => panic!("Could not find pinned_call: {}", error),
+            Ok(pinned_call) => unsafe {
+                mem::transmute::<*mut u8, extern "C" fn() -> i32>(pinned_call)
+            },
+        };
+
+        // Releases the only reference once the pin is taken, and before
+        // `pinned_call` runs
+        let handle = lib.handle.as_ptr() as usize;
+        let (pinned, on_pinned) = mpsc::channel();
+        let (closed, on_closed) = mpsc::channel();
+        let competitor = thread::spawn(move || {
+            if on_pinned.recv().is_ok() {
+                let result = unsafe { libc::dlclose(handle as *mut libc::c_void) };
+                let _ = closed.send(result);
+            }
+        });
+        let called = lib.with_pinned(|| {
+            let _ = pinned.send(());
+            assert_eq!(on_closed.recv().ok(), Some(0));
+            pinned_call()
+        });
+        assert_eq!(called, 42);
+        assert!(competitor.join().is_ok());
+        lib.closed = true;
+
+        // Dropping the pin was the last reference, so the image is gone
+        let Ok(raw_path) = CString::new(path.as_os_str().as_encoded_bytes()) else {
+            panic!("The fixture path contains a NUL byte");
+        };
+        let reopened =
+            unsafe { libc::dlopen(raw_path.as_ptr(), libc::RTLD_LAZY | libc::RTLD_NOLOAD) };
+        assert!(reopened.is_null());
+    }
+
+    #[test]
+    fn test_handle_search_path() {
+        // glibc reads the search path variable once at startup, so the
+        // directory is prepended through the library's runpath instead
+        let dir = test_support::fixture_dir().join("handle_search_path");
+        let path = test_support::build_library_in(
+            &dir,
+            "search_path",
+            "int search_path(void) { return 1; }",
+            &[&format!("-Wl,-rpath,{}", dir.display())],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        match lib.handle_search_path() {
+            Err(error) => panic!("Could not query the search path: {}", error),
+            Ok(dirs) => assert!(dirs.contains(&dir), "{dir:?} missing from {dirs:?}"),
+        }
+    }
+
+    #[test]
+    fn test_tls_template() {
+        let path = test_support::build_library(
+            "tls_template",
+            "__thread char buf[8] = \"abc\";\n\
+             __thread int zeroed[2];\n\
+             int not_thread_local = 1;\n",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(lib.tls_template("buf"), Ok(b"abc\0\0\0\0\0".to_vec()));
+        assert_eq!(lib.tls_template("zeroed"), Ok(vec![0; 8]));
+        assert!(lib.tls_template("not_thread_local").is_err());
+        assert!(matches!(
+            lib.tls_template("missing"),
+            Err(Error::SymbolMissing { .. })
+        ));
+    }
+
+    #[test]
+    fn test_prefault() {
+        let path = test_support::build_library(
+            "prefault",
+            "static char table[1 << 16] = { 1 };\n\
+             int prefault_probe(int i) { return table[i]; }\n",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(lib.prefault(), Ok(()));
+    }
+}
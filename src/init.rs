@@ -0,0 +1,112 @@
+//! One-time initialization of loaded libraries shared by the whole process
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex, Once, PoisonError},
+};
+
+use crate::{DynamicLibrary, Error};
+
+/// The initializers already run, or running, keyed by library handle and
+/// initializer name, along with the address of the initializer, which tells
+/// whether the library is still loaded. See [`forget_unloaded`]
+static INITIALIZED: LazyLock<Mutex<Initializers>> = LazyLock::new(Mutex::default);
+
+type Initializers = HashMap<(usize, String), (usize, Arc<Once>)>;
+
+/// Drops the initializers recorded for `handle` once closing it unloaded the
+/// library, so a library loaded again, which may get the same handle back,
+/// is initialized again
+pub(crate) fn forget_unloaded(handle: *mut u8) {
+    let mut initialized = INITIALIZED.lock().unwrap_or_else(PoisonError::into_inner);
+    initialized.retain(|(key, _), (init, _)| {
+        *key != handle as usize || crate::symbol_info(*init as *const ()).is_some()
+    });
+}
+
+impl DynamicLibrary {
+    /// Calls the library's `extern "C" fn()` export named `init_symbol`
+    /// unless it was already called through this method, from any thread
+    /// and any handle to the same loaded library. Concurrent callers wait
+    /// for the first call to finish.
+    ///
+    /// The record is dropped when closing a handle unloads the library, so
+    /// a library loaded again is initialized again. A library unloaded by
+    /// other means, such as C code calling `dlclose`, keeps its record.
+    /// Fails with [`Error::NullSymbol`] if the export's value is null
+    ///
+    /// # Safety
+    ///
+    /// `init_symbol` must be an `extern "C" fn()` safe to call from any
+    /// thread
+    pub unsafe fn ensure_initialized(&self, init_symbol: &str) -> Result<(), Error> {
+        let init = unsafe { self.get_fn::<extern "C" fn()>(init_symbol)? };
+        let once = {
+            let mut initialized = INITIALIZED.lock().unwrap_or_else(PoisonError::into_inner);
+            let key = (self.handle.as_ptr() as usize, init_symbol.to_string());
+            let (_, once) = initialized
+                .entry(key)
+                .or_insert_with(|| (init as usize, Arc::new(Once::new())));
+            Arc::clone(once)
+        };
+        once.call_once(|| init());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{mem, thread};
+
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn test_ensure_initialized() {
+        let path = test_support::build_library(
+            "ensure_initialized",
+            "static int initialized;\n\
+             void plugin_init(void) { __atomic_add_fetch(&initialized, 1, __ATOMIC_SEQ_CST); }\n\
+             int init_count(void) { return initialized; }\n\
+             __asm__(\".globl null_init\\n.set null_init, 0\");\n",
+            &[],
+        );
+        let open = || match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        let lib = open();
+
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    // Each thread opens its own handle to the same library
+                    let lib = open();
+                    assert_eq!(unsafe { lib.ensure_initialized("plugin_init") }, Ok(()));
+                });
+            }
+        });
+        let init_count = |lib: &DynamicLibrary| match unsafe { lib.symbol::<u8>("init_count") } {
+            Err(error) => panic!("Could not find init_count: {}", error),
+            Ok(symbol) => unsafe { mem::transmute::<*mut u8, extern "C" fn() -> i32>(symbol)() },
+        };
+        assert_eq!(unsafe { lib.ensure_initialized("plugin_init") }, Ok(()));
+        assert_eq!(init_count(&lib), 1);
+        assert!(unsafe { lib.ensure_initialized("not_an_initializer") }.is_err());
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            unsafe { lib.ensure_initialized("null_init") },
+            Err(Error::NullSymbol {
+                name: "null_init".to_string()
+            })
+        );
+
+        // Closing the last handle unloads the library, and loading it
+        // again, possibly at the same address, resets its state
+        assert_eq!(lib.close_owned(), Ok(()));
+        let lib = open();
+        assert_eq!(init_count(&lib), 0);
+        assert_eq!(unsafe { lib.ensure_initialized("plugin_init") }, Ok(()));
+        assert_eq!(init_count(&lib), 1);
+    }
+}
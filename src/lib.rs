@@ -15,21 +15,225 @@
 //! Linux and macOS only
 
 use std::{
+    collections::HashSet,
     env,
-    ffi::{CString, OsString},
-    mem,
+    ffi::{CStr, CString, OsStr, OsString},
+    fmt, fs, io, mem,
+    os::{
+        fd::AsRawFd,
+        unix::{ffi::OsStrExt, fs::PermissionsExt},
+    },
     path::{Path, PathBuf},
+    pin::Pin,
+    ptr::{self, NonNull},
+    sync::{
+        atomic::{AtomicPtr, AtomicU8, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, SystemTime},
 };
 
+pub use address::{SymbolInfo, nearest_symbol, symbol_info};
+pub use bundle::pack_bundle;
+#[cfg(feature = "libffi")]
+pub use callable::{CType, CValue, Callable, FnSignature};
+#[cfg(feature = "derive")]
+pub use dynamite_derive::DylibApi;
+#[cfg(target_os = "linux")]
+pub use elf::{
+    DependencyNode, dependencies, dependency_tree, exported_symbols_file, exports_added_removed,
+    is_position_independent, required_os_abi, runpath, undefined_symbols,
+};
+pub use error::Error;
+#[cfg(target_os = "linux")]
+pub use image::{SymbolType, Visibility};
+#[cfg(target_os = "macos")]
+pub use loaded::NamespaceKind;
+#[cfg(target_os = "linux")]
+pub use loaded::find_symbol_conflicts;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub use loaded::{
+    LoadedImage, clear_global_symbol_cache, find_loaded, loaded_libraries, registered_images,
+};
+pub use options::{
+    Binding, OpenOptions, TempConfig, clear_path_rewriter, native_open_flags, set_default_binding,
+    set_path_rewriter, set_strict_paths, set_temp_config,
+};
+pub use plugin::{PLUGIN_DESCRIPTOR_MAGIC, PLUGIN_DESCRIPTOR_SYMBOL, PluginId, PluginManager};
+#[cfg(target_os = "linux")]
+pub use reflect::LibraryReflection;
+#[cfg(feature = "regex")]
+pub use regex;
+#[cfg(target_os = "linux")]
+pub use resolve::{explain_search, resolve_library_path};
+#[cfg(feature = "semver")]
+pub use semver;
+pub use symbol::{DylibApi, FnPtr, Symbol};
+pub use symbol_table::SymbolTable;
+
+mod address;
+mod bindings;
+mod bundle;
+#[cfg(feature = "libffi")]
+mod callable;
+#[cfg(target_os = "linux")]
+mod elf;
+mod error;
+#[cfg(target_os = "linux")]
+mod image;
+mod init;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod loaded;
+#[cfg(target_os = "linux")]
+mod numa;
+mod options;
+mod plugin;
+#[cfg(target_os = "linux")]
+mod reflect;
+#[cfg(target_os = "linux")]
+mod resolve;
+mod symbol;
+mod symbol_table;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "semver")]
+mod version;
+
+/// The exact value of the dynamic library search path environment variable
+/// at some point in time, including whether it was set at all
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchPathSnapshot {
+    value: Option<OsString>,
+}
+
+/// Which symbols a handle to the running process resolves, see
+/// [`DynamicLibrary::open_process`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessScope {
+    /// The executable, its dependencies and every library opened with global
+    /// scope, the same as `open(None)`
+    Global,
+    /// Only the symbols defined by the main executable itself
+    MainOnly,
+}
+
+/// Number of live [`DynamicLibrary`] values, see [`open_handle_count`]
+static OPEN_HANDLES: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns how many [`DynamicLibrary`] values are currently alive, for
+/// spotting handles that leak in long-running hosts. Libraries given up
+/// with [`DynamicLibrary::leak`] or [`DynamicLibrary::into_raw`] are no
+/// longer counted. A relaxed counter,
+/// only meant for observation
+pub fn open_handle_count() -> usize {
+    OPEN_HANDLES.load(Ordering::Relaxed)
+}
+
+/// What [`DynamicLibrary::reopen_with`] does when closing the old handle
+/// fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseFailurePolicy {
+    /// Give up on the reopen and return the close error
+    Abort,
+    /// Open the library again anyway and return the close error alongside
+    Continue,
+}
+
+/// What dropping a [`DynamicLibrary`] does when closing its handle fails,
+/// see [`set_drop_error_policy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropErrorPolicy {
+    /// Discard the error, the default
+    Ignore,
+    /// Write the error to standard error
+    Print,
+    /// Panic with the error, unless the thread is already panicking, where
+    /// a second panic would abort the process
+    Panic,
+}
+
+static DROP_ERROR_POLICY: AtomicU8 = AtomicU8::new(DropErrorPolicy::Ignore as u8);
+
+/// Sets what dropping a library does when closing it fails, for the whole
+/// process. Use [`DynamicLibrary::close_owned`] or
+/// [`DynamicLibrary::close`] to handle the error instead
+pub fn set_drop_error_policy(policy: DropErrorPolicy) {
+    DROP_ERROR_POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+/// Handles `error`, raised while closing a handle from a destructor, as the
+/// [`DropErrorPolicy`] in effect says
+pub(crate) fn report_drop_error(error: &Error) {
+    match DROP_ERROR_POLICY.load(Ordering::Relaxed) {
+        policy if policy == DropErrorPolicy::Print as u8 => {
+            eprintln!("failed to close a dynamic library: {error}");
+        }
+        policy if policy == DropErrorPolicy::Panic as u8 && !thread::panicking() => {
+            panic!("{}", error);
+        }
+        _ => {}
+    }
+}
+
 pub struct DynamicLibrary {
-    handle: *mut u8,
+    handle: NonNull<u8>,
+    flags: libc::c_int,
+    main_only: bool,
+    closed: bool,
+    finalizer: Option<extern "C" fn()>,
+    on_close: Option<Box<dyn FnOnce() + Send>>,
+    /// A file kept open while the library is loaded: the one holding the
+    /// lock of [`DynamicLibrary::open_locked`], or the in-memory file
+    /// [`DynamicLibrary::open_embedded`] loaded the library through
+    file: Option<fs::File>,
+    path: Option<PathBuf>,
+    /// Whether this is a handle to the calling process rather than to a
+    /// library
+    process: bool,
 }
 
 impl Drop for DynamicLibrary {
     fn drop(&mut self) {
-        match dl::check_for_errors_in(|| unsafe { dl::close(self.handle) }) {
-            Ok(()) => {}
-            Err(str) => panic!("{}", str),
+        OPEN_HANDLES.fetch_sub(1, Ordering::Relaxed);
+        if self.closed {
+            return;
+        }
+        self.finalize();
+        match dl::check_for_errors_in(|| unsafe { dl::close(self.handle.as_ptr()) }) {
+            Ok(()) => init::forget_unloaded(self.handle.as_ptr()),
+            Err(error) => report_drop_error(&error),
+        }
+    }
+}
+
+// SAFETY: the handle is only passed to `dlsym`, `dladdr`, `dlinfo` and
+// `dlclose`, which the platform loaders serialize behind their own lock, and
+// errors are read back with `dlerror`, which reports the last error of the
+// calling thread. Every other field is `Send`, and none is mutated through a
+// shared reference, so a library can be moved to and shared between threads
+unsafe impl Send for DynamicLibrary {}
+unsafe impl Sync for DynamicLibrary {}
+
+/// Shows the path the library came from, see [`DynamicLibrary::path`], so
+/// logs can tell libraries apart
+impl fmt::Debug for DynamicLibrary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynamicLibrary")
+            .field("path", &self.path())
+            .field("handle", &self.handle)
+            .field("closed", &self.closed)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Shows the path the library came from, see [`DynamicLibrary::path`], or
+/// `<unknown>` when it is not known
+impl fmt::Display for DynamicLibrary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path() {
+            Some(path) => write!(f, "{}", path.display()),
+            None => f.write_str("<unknown>"),
         }
     }
 }
@@ -44,16 +248,646 @@ impl DynamicLibrary {
     // passed by reference. (Here: in the `open` method.)
 
     /// Lazily open a dynamic library. When passed None it gives a
-    /// handle to the calling process
-    pub fn open(filename: Option<&Path>) -> Result<DynamicLibrary, String> {
-        let maybe_library = dl::open(filename.map(|path| path.as_os_str()));
+    /// handle to the calling process. Binds eagerly instead when
+    /// [`OpenOptions::FORCE_NOW_VAR`] is set
+    pub fn open(filename: Option<&Path>) -> Result<DynamicLibrary, Error> {
+        let rewritten = filename.and_then(options::rewrite_path);
+        DynamicLibrary::open_rewritten(rewritten.as_deref().or(filename))
+    }
+
+    /// Same as [`DynamicLibrary::open`] for a path the rewriter, if any,
+    /// was already applied to
+    fn open_rewritten(filename: Option<&Path>) -> Result<DynamicLibrary, Error> {
+        options::check_strict_path(filename)?;
+        let flags = options::default_binding();
+        let maybe_library = dl::open(filename.map(|path| path.as_os_str()), flags);
 
         // The dynamic library must not be constructed if there is
         // an error opening the library so the destructor does not
         // run.
-        match maybe_library {
-            Err(err) => Err(err),
-            Ok(handle) => Ok(DynamicLibrary { handle }),
+        maybe_library.and_then(|handle| DynamicLibrary::from_opened(handle, flags, filename))
+    }
+
+    /// Lazily opens the library at the absolute path `filename`, failing
+    /// with [`Error::RelativePathRejected`] for relative paths and bare
+    /// file names, which the loader would search for
+    pub fn open_absolute(filename: &Path) -> Result<DynamicLibrary, Error> {
+        options::check_absolute(filename)?;
+        DynamicLibrary::open(Some(filename))
+    }
+
+    /// Lazily opens the library `filename` found in `dir`, by the absolute
+    /// path the two make, without changing the current directory of the
+    /// process. A relative `dir` is taken relative to the current directory,
+    /// read once; an absolute `filename` is opened as is
+    pub fn open_relative_to(dir: &Path, filename: &Path) -> Result<DynamicLibrary, Error> {
+        let path = dir.join(filename);
+        let path = std::path::absolute(&path).map_err(|err| Error::io(&path, &err))?;
+        DynamicLibrary::open(Some(&path))
+    }
+
+    /// Lazily opens the library at `filename` only if neither the file nor
+    /// its directory could be replaced by another user: both must be owned
+    /// by root or the effective user and not be writable by their group or
+    /// others, or this fails with [`Error::InsecurePermissions`]. Symbolic
+    /// links are resolved first and the file they lead to is checked and
+    /// opened, after any [`set_path_rewriter`] rewriter, so the file checked
+    /// is the one loaded. The file could still be swapped between the check
+    /// and the load by someone allowed to write to it
+    pub fn open_secure(filename: &Path) -> Result<DynamicLibrary, Error> {
+        let rewritten = options::rewrite_path(filename);
+        let filename = rewritten.as_deref().unwrap_or(filename);
+        let path = fs::canonicalize(filename).map_err(|err| Error::io(filename, &err))?;
+        if let Some(dir) = path.parent() {
+            options::check_secure(dir)?;
+        }
+        options::check_secure(&path)?;
+        DynamicLibrary::open_rewritten(Some(&path))
+    }
+
+    /// Lazily opens the library at `filename` in a new link-map namespace
+    /// (`dlmopen`), with its own copy of every dependency, libc included,
+    /// so it cannot see or interpose on the symbols of the rest of the
+    /// process. glibc supports only a handful of namespaces per process.
+    /// Linux only
+    #[cfg(target_os = "linux")]
+    pub fn open_in_new_namespace(filename: &Path) -> Result<DynamicLibrary, Error> {
+        DynamicLibrary::open_in_new_namespace_with_preload(filename, &[])
+    }
+
+    /// Same as [`DynamicLibrary::open_in_new_namespace`] but first loads
+    /// `preload`, in order, into the new namespace, to hand a sandboxed
+    /// plugin stubs or replacements of what it would otherwise get from the
+    /// system. The first library starts the namespace, so the plugin binds
+    /// to its definitions ahead of those of its own dependencies, the way
+    /// `LD_PRELOAD` works for a process. glibc does not let the others join
+    /// that global scope; they stand in for the plugin's dependencies with
+    /// the same soname instead of the files the loader would find.
+    ///
+    /// The preloaded libraries stay loaded as long as the process runs. As
+    /// the handle is bound to its namespace, it cannot be reopened, and
+    /// [`DynamicLibrary::opened_path`] is `None`. Every path goes through
+    /// [`set_path_rewriter`] and [`set_strict_paths`] as with
+    /// [`DynamicLibrary::open`]. Linux only
+    #[cfg(target_os = "linux")]
+    pub fn open_in_new_namespace_with_preload(
+        filename: &Path,
+        preload: &[&Path],
+    ) -> Result<DynamicLibrary, Error> {
+        // Each path is rewritten and checked as `open` would, before any is
+        // loaded
+        let rewrite = |path: &Path| {
+            let path = options::rewrite_path(path).unwrap_or_else(|| path.to_path_buf());
+            options::check_strict_path(Some(&path)).map(|()| path)
+        };
+        let preload = preload
+            .iter()
+            .map(|library| rewrite(library))
+            .collect::<Result<Vec<_>, _>>()?;
+        let filename = rewrite(filename)?;
+        let flags = options::default_binding();
+        let mut namespace = None;
+        for library in &preload {
+            // Kept loaded, as what binds to them does not hold a reference
+            let (handle, opened_in) =
+                dl::open_in_namespace(namespace, library.as_os_str(), flags | dl::NODELETE)?;
+            unsafe { dl::close(handle) };
+            namespace = Some(opened_in);
+        }
+        let (handle, _) = dl::open_in_namespace(namespace, filename.as_os_str(), flags)?;
+        DynamicLibrary::from_handle(handle, flags, None)
+    }
+
+    /// Heuristically reports whether this process may load libraries at
+    /// runtime: the platform must allow loading arbitrary files, which iOS
+    /// does not, and the loader must hand out a handle to the process. A
+    /// `true` does not guarantee a given file loads, as sandbox or SELinux
+    /// policies may still reject it
+    pub fn loading_supported() -> bool {
+        if cfg!(target_os = "ios") {
+            return false;
+        }
+        DynamicLibrary::open(None).is_ok()
+    }
+
+    /// Reports whether the process runs under AddressSanitizer or another
+    /// sanitizer with a runtime that interposes libc functions, detected by
+    /// the runtime's initialization symbol in the global scope. Lookups of
+    /// those functions may then return the runtime's wrappers, see
+    /// `DynamicLibrary::symbol_uninterposed` on Linux
+    pub fn running_under_sanitizer() -> bool {
+        [
+            c"__asan_init",
+            c"__hwasan_init",
+            c"__msan_init",
+            c"__tsan_init",
+        ]
+        .iter()
+        .any(|name| {
+            dl::check_for_errors_in(|| unsafe {
+                dl::symbol(libc::RTLD_DEFAULT.cast(), name.as_ptr())
+            })
+            .is_ok()
+        })
+    }
+
+    /// Returns a builder to open a library with non-default flags:
+    ///
+    /// ```
+    /// # use std::path::Path;
+    /// use dynamite::DynamicLibrary;
+    ///
+    /// # #[cfg(target_os = "linux")]
+    /// # let path = Path::new("libm.so.6");
+    /// # #[cfg(not(target_os = "linux"))]
+    /// # let path = Path::new("libm.dylib");
+    /// let libm = DynamicLibrary::options().now(true).global(true).open(Some(path))?;
+    /// # Ok::<(), dynamite::Error>(())
+    /// ```
+    pub const fn options() -> OpenOptions {
+        OpenOptions::new()
+    }
+
+    fn from_handle(
+        handle: *mut u8,
+        flags: libc::c_int,
+        path: Option<PathBuf>,
+    ) -> Result<DynamicLibrary, Error> {
+        Ok(DynamicLibrary::from_non_null(
+            dl::non_null(handle)?,
+            flags,
+            path,
+        ))
+    }
+
+    /// Same as `from_handle` for the handle `dlopen` returned for
+    /// `filename`, a handle to the process when `None`
+    pub(crate) fn from_opened(
+        handle: *mut u8,
+        flags: libc::c_int,
+        filename: Option<&Path>,
+    ) -> Result<DynamicLibrary, Error> {
+        let mut lib = DynamicLibrary::from_handle(handle, flags, filename.map(Path::to_path_buf))?;
+        lib.process = filename.is_none();
+        Ok(lib)
+    }
+
+    fn from_non_null(
+        handle: NonNull<u8>,
+        flags: libc::c_int,
+        path: Option<PathBuf>,
+    ) -> DynamicLibrary {
+        OPEN_HANDLES.fetch_add(1, Ordering::Relaxed);
+        DynamicLibrary {
+            handle,
+            flags,
+            main_only: false,
+            closed: false,
+            finalizer: None,
+            on_close: None,
+            file: None,
+            path,
+            process: false,
+        }
+    }
+
+    /// The path the library was opened from, `None` for handles to the
+    /// process and for libraries opened with [`DynamicLibrary::open_cstr`]
+    pub fn opened_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Where the library came from: the path it was opened from, as
+    /// [`DynamicLibrary::opened_path`] reports, or `self` for a handle to
+    /// the process. `None` when it is not known, as for libraries opened
+    /// with [`DynamicLibrary::open_cstr`] or [`DynamicLibrary::from_raw`].
+    /// Reported by [`Error::SymbolMissing`] and the `Debug` and `Display`
+    /// impls
+    pub fn path(&self) -> Option<&Path> {
+        match &self.path {
+            Some(path) => Some(path),
+            None if self.process => Some(Path::new("self")),
+            None => None,
+        }
+    }
+
+    /// The options the handle was opened with, as the crate passed them to
+    /// the loader rather than as the loader tracks the library, which may
+    /// have been opened by others with different flags. The binding is always
+    /// explicit, resolved from [`set_default_binding`] and
+    /// [`OpenOptions::FORCE_NOW_VAR`] at the time of opening, and becomes
+    /// eager after [`DynamicLibrary::bind_now`]
+    pub const fn open_flags(&self) -> OpenOptions {
+        OpenOptions::from_native(self.flags)
+    }
+
+    /// Closes the library and opens it again from [`opened_path`] with the
+    /// same flags, loading the current contents of the file if nothing else
+    /// kept the old image loaded. Runs the finalizer, if any, which the new
+    /// handle does not inherit. The lock of [`DynamicLibrary::open_locked`]
+    /// is held throughout. On error the handle is left closed
+    ///
+    /// [`opened_path`]: DynamicLibrary::opened_path
+    pub fn reopen(&mut self) -> Result<(), Error> {
+        self.reopen_with(CloseFailurePolicy::Abort).map(|_| ())
+    }
+
+    /// Same as [`DynamicLibrary::reopen`] but lets `policy` decide whether
+    /// a failure to close the old handle aborts the reopen. With
+    /// [`CloseFailurePolicy::Continue`] the library is opened again anyway
+    /// and the close error, if any, is returned; the old handle may then
+    /// keep the old image loaded
+    pub fn reopen_with(&mut self, policy: CloseFailurePolicy) -> Result<Option<Error>, Error> {
+        self.reopen_closing(policy, DynamicLibrary::close)
+    }
+
+    fn reopen_closing(
+        &mut self,
+        policy: CloseFailurePolicy,
+        close: impl FnOnce(&mut DynamicLibrary) -> Result<(), Error>,
+    ) -> Result<Option<Error>, Error> {
+        let Some(path) = self.path.clone() else {
+            return Err(Error::Unsupported("reopening a library without a path"));
+        };
+        // The file locked by `open_locked` is kept open across the close,
+        // so the lock is held throughout, and the library is loaded through
+        // it again
+        let file = self.file.take();
+        let close_error = if self.closed { None } else { close(self).err() };
+        if let (Some(err), CloseFailurePolicy::Abort) = (&close_error, policy) {
+            return Err(err.clone());
+        }
+        let path = match &file {
+            Some(file) if cfg!(target_os = "linux") => {
+                PathBuf::from(format!("/proc/self/fd/{}", file.as_raw_fd()))
+            }
+            _ => path,
+        };
+        self.handle = dl::non_null(dl::open(Some(path.as_os_str()), self.flags)?)?;
+        self.closed = false;
+        self.file = file;
+        Ok(close_error)
+    }
+
+    /// Reopens the library, as [`DynamicLibrary::reopen`] does, if its file
+    /// was modified after `last_mtime`, then records the new modification
+    /// time there. Returns whether the library was reloaded. Meant for live
+    /// reloading during development
+    pub fn reload_if_changed(&mut self, last_mtime: &mut SystemTime) -> Result<bool, Error> {
+        let Some(path) = self.opened_path() else {
+            return Err(Error::Unsupported("reloading a library without a path"));
+        };
+        let mtime = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|err| Error::io(path, &err))?;
+        if mtime <= *last_mtime {
+            return Ok(false);
+        }
+        self.reopen()?;
+        *last_mtime = mtime;
+        Ok(true)
+    }
+
+    /// Reopens the library, as [`DynamicLibrary::reopen`] does, bound
+    /// eagerly (`RTLD_NOW`) from then on, so that every undefined symbol is
+    /// resolved again right away. Fails, leaving the handle closed, when one
+    /// cannot be. The loader keeps the existing image and its lazy bindings
+    /// if another handle still holds the library open
+    pub fn bind_now(&mut self) -> Result<(), Error> {
+        if self.closed {
+            return Err(Error::HandleClosed);
+        }
+        self.flags = (self.flags & !dl::LAZY) | dl::NOW;
+        self.reopen()
+    }
+
+    /// Lazily opens a dynamic library whose `extern "C" fn()` export named
+    /// `finalizer_symbol` is called right before the handle is closed, by
+    /// [`DynamicLibrary::close`] or on drop. The finalizer is resolved here,
+    /// so a missing symbol, or one whose value is null, fails the open
+    /// rather than the unload
+    ///
+    /// # Safety
+    ///
+    /// `finalizer_symbol` must be an `extern "C" fn()` safe to call from
+    /// whichever thread closes or drops the handle
+    pub unsafe fn open_with_finalizer(
+        filename: &Path,
+        finalizer_symbol: &str,
+    ) -> Result<DynamicLibrary, Error> {
+        let mut lib = DynamicLibrary::open(Some(filename))?;
+        lib.finalizer = Some(unsafe { lib.get_fn::<extern "C" fn()>(finalizer_symbol)? });
+        Ok(lib)
+    }
+
+    /// Registers `f` to be called right before the handle is closed, by
+    /// [`DynamicLibrary::close`] or on drop, after the finalizer if any,
+    /// while the library's code can still be called. Only one callback is
+    /// kept: registering another replaces it
+    pub fn on_close(&mut self, f: impl FnOnce() + Send + 'static) {
+        self.on_close = Some(Box::new(f));
+    }
+
+    /// Runs the finalizer and the close callback, if any, at most once
+    fn finalize(&mut self) {
+        if let Some(finalizer) = self.finalizer.take() {
+            finalizer();
+        }
+        if let Some(on_close) = self.on_close.take() {
+            on_close();
+        }
+    }
+
+    /// Gives a handle to the calling process whose symbol lookups cover
+    /// `scope`.
+    ///
+    /// Linux refuses to `dlopen` a position-independent executable by path,
+    /// so there [`ProcessScope::MainOnly`] uses the process handle and
+    /// rejects symbols defined outside of the executable. On macOS it opens
+    /// the executable from [`env::current_exe`] with `RTLD_FIRST`
+    pub fn open_process(scope: ProcessScope) -> Result<DynamicLibrary, Error> {
+        match scope {
+            ProcessScope::Global => DynamicLibrary::open(None),
+            #[cfg(target_os = "linux")]
+            ProcessScope::MainOnly => {
+                let mut lib = DynamicLibrary::open(None)?;
+                lib.main_only = true;
+                Ok(lib)
+            }
+            #[cfg(target_os = "macos")]
+            ProcessScope::MainOnly => {
+                let exe =
+                    env::current_exe().map_err(|err| Error::io(Path::new("current_exe"), &err))?;
+                let flags = options::default_binding() | libc::RTLD_FIRST;
+                dl::open(Some(exe.as_os_str()), flags)
+                    .and_then(|handle| DynamicLibrary::from_handle(handle, flags, Some(exe)))
+            }
+            #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+            ProcessScope::MainOnly => Err(Error::Unsupported("ProcessScope::MainOnly")),
+        }
+    }
+
+    /// Returns the platform's file name for the library `name`, such as
+    /// `libname.so` on Linux, `libname.dylib` on macOS and `name.dll` on
+    /// Windows
+    pub fn library_filename(name: &str) -> OsString {
+        format!(
+            "{}{name}{}",
+            env::consts::DLL_PREFIX,
+            env::consts::DLL_SUFFIX
+        )
+        .into()
+    }
+
+    /// Returns the file names the loader accepts for the library `stem`
+    /// with the given major version, unversioned first: `libfoo.so` and
+    /// `libfoo.so.3` on ELF platforms, `libfoo.dylib` and `libfoo.3.dylib`
+    /// on macOS
+    pub fn soname_candidates(stem: &str, major: Option<u32>) -> Vec<OsString> {
+        let mut candidates = vec![DynamicLibrary::library_filename(stem)];
+        if let Some(major) = major {
+            if cfg!(target_os = "macos") {
+                candidates.push(format!("lib{stem}.{major}.dylib").into());
+            } else {
+                candidates.push(format!("lib{stem}.so.{major}").into());
+            }
+        }
+        candidates
+    }
+
+    /// Lazily opens the library `filename` from the directory of the
+    /// running executable
+    pub fn open_beside_exe(filename: impl AsRef<Path>) -> Result<DynamicLibrary, Error> {
+        let exe = env::current_exe().map_err(|err| Error::io(Path::new("current_exe"), &err))?;
+        let dir = exe.parent().unwrap_or_else(|| Path::new("/"));
+        DynamicLibrary::open(Some(&dir.join(filename)))
+    }
+
+    /// Lazily opens a dynamic library on a separate thread, failing with
+    /// [`Error::Timeout`] if the load takes longer than `timeout`, as it
+    /// may on a stalled network filesystem.
+    ///
+    /// `dlopen` cannot be cancelled, so after a timeout the thread keeps
+    /// waiting for the load, and any library constructors still run. A
+    /// library loaded that late is closed again right away
+    pub fn open_with_timeout(
+        filename: PathBuf,
+        timeout: Duration,
+    ) -> Result<DynamicLibrary, Error> {
+        with_timeout(timeout, move || DynamicLibrary::open(Some(&filename)))?
+    }
+
+    /// Lazily opens a private copy of the library `filename`, so the file
+    /// can be overwritten while the copy stays loaded, and each call loads
+    /// the current contents of the file as a new image instead of getting
+    /// the one already loaded.
+    ///
+    /// This is the shadow copy trick Windows hosts use to get around loaded
+    /// DLLs being locked on disk. Here the copy lives in a new directory
+    /// only the current user can access, created as set with
+    /// [`set_temp_config`], which is removed again once the
+    /// copy is loaded or fails to; the loaded image stays valid. Since the
+    /// copy is loaded from that directory, dependencies found relative to
+    /// the library, through an `$ORIGIN` runpath, are looked up there and
+    /// not found. [`DynamicLibrary::opened_path`] reports `filename`
+    pub fn open_shadow_copy(filename: &Path) -> Result<DynamicLibrary, Error> {
+        let dir = bundle::private_temp_dir()?;
+        let copy = dir.join(filename.file_name().unwrap_or_else(|| "library".as_ref()));
+        let mode = fs::Permissions::from_mode(options::temp_config().mode);
+        let lib = fs::copy(filename, &copy)
+            .and_then(|_| fs::set_permissions(&copy, mode))
+            .map_err(|err| Error::io(filename, &err))
+            .and_then(|_| DynamicLibrary::open(Some(&copy)));
+        let _ = fs::remove_dir_all(&dir);
+        lib.map(|mut lib| {
+            lib.path = Some(filename.to_path_buf());
+            lib
+        })
+    }
+
+    /// Lazily opens the library at `filename` while holding a shared `flock`
+    /// on the file until the handle is closed or dropped, so a process that
+    /// takes an exclusive lock before replacing the file, such as an
+    /// updater, fails or waits until the library is unloaded. Fails instead
+    /// of waiting if the file is already locked exclusively.
+    ///
+    /// Advisory locks are cooperative: a process writing to the file without
+    /// asking for a lock is not stopped. On Linux the library is loaded
+    /// through the locked descriptor, as `/proc/self/fd/<fd>`, so the file
+    /// loaded is the one locked even if `filename` is replaced meanwhile;
+    /// the loader then reports that name, but
+    /// [`DynamicLibrary::opened_path`] reports `filename`
+    pub fn open_locked(filename: &Path) -> Result<DynamicLibrary, Error> {
+        let file = fs::File::open(filename).map_err(|err| Error::io(filename, &err))?;
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_SH | libc::LOCK_NB) } != 0 {
+            return Err(Error::io(filename, &io::Error::last_os_error()));
+        }
+        let mut lib = if cfg!(target_os = "linux") {
+            let fd_path = PathBuf::from(format!("/proc/self/fd/{}", file.as_raw_fd()));
+            DynamicLibrary::open(Some(&fd_path))?
+        } else {
+            DynamicLibrary::open(Some(filename))?
+        };
+        lib.path = Some(filename.to_path_buf());
+        lib.file = Some(file);
+        Ok(lib)
+    }
+
+    /// Lazily opens the first of `paths` that loads, trying them in order
+    pub fn open_first<P: AsRef<Path>>(paths: &[P]) -> Result<DynamicLibrary, Error> {
+        let mut failures = Vec::with_capacity(paths.len());
+        for path in paths {
+            match DynamicLibrary::open(Some(path.as_ref())) {
+                Ok(lib) => return Ok(lib),
+                Err(err) => failures.push(err),
+            }
+        }
+        Err(Error::NoCandidateResolved(failures))
+    }
+
+    /// Lazily open a dynamic library from an already NUL-terminated path.
+    /// When passed None it gives a handle to the calling process.
+    ///
+    /// Unlike [`DynamicLibrary::open`] this does not allocate: on the Unix
+    /// platforms supported here the bytes are handed to `dlopen` as is,
+    /// unless a [`set_path_rewriter`] rewriter is set. A Windows backend
+    /// would still have to convert them to UTF-16
+    pub fn open_cstr(filename: Option<&CStr>) -> Result<DynamicLibrary, Error> {
+        let path = filename.map(|filename| Path::new(OsStr::from_bytes(filename.to_bytes())));
+        let flags = options::default_binding();
+        if let Some(rewritten) = path.and_then(options::rewrite_path) {
+            options::check_strict_path(Some(&rewritten))?;
+            return dl::open(Some(rewritten.as_os_str()), flags)
+                .and_then(|handle| DynamicLibrary::from_handle(handle, flags, None));
+        }
+        options::check_strict_path(path)?;
+        let handle = dl::open_cstr(filename, flags)?;
+        let mut lib = DynamicLibrary::from_handle(handle, flags, None)?;
+        lib.process = filename.is_none();
+        Ok(lib)
+    }
+
+    /// Closes the library handle. Any later symbol lookup through this
+    /// wrapper fails with [`Error::HandleClosed`], and dropping it no
+    /// longer closes the handle a second time
+    pub fn close(&mut self) -> Result<(), Error> {
+        if self.closed {
+            return Err(Error::HandleClosed);
+        }
+        self.closed = true;
+        self.finalize();
+        let closed = dl::check_for_errors_in(|| unsafe { dl::close(self.handle.as_ptr()) });
+        if closed.is_ok() {
+            init::forget_unloaded(self.handle.as_ptr());
+        }
+        self.file = None;
+        closed
+    }
+
+    /// Closes the library handle and consumes the library, returning the
+    /// error dropping it would only handle as set with
+    /// [`set_drop_error_policy`]
+    pub fn close_owned(mut self) -> Result<(), Error> {
+        self.close()
+    }
+
+    /// Keeps the library loaded for the rest of the process, returning a
+    /// handle that lives as long. The library is never unloaded
+    pub fn leak(self) -> &'static DynamicLibrary {
+        OPEN_HANDLES.fetch_sub(1, Ordering::Relaxed);
+        Box::leak(Box::new(self))
+    }
+
+    /// Returns the native handle, as returned by `dlopen`, to pass to C
+    /// code expecting one. It stays owned by the library, so it must not be
+    /// closed, nor used once the library is closed or dropped
+    pub const fn as_raw(&self) -> *mut libc::c_void {
+        self.handle.as_ptr().cast()
+    }
+
+    /// Gives up the native handle, as returned by `dlopen`, without closing
+    /// it, so it can be handed to C code that closes it, or turned back into
+    /// a library with [`DynamicLibrary::from_raw`]. Returns null if the
+    /// library was already closed. The finalizer and the
+    /// [`DynamicLibrary::on_close`] hook are dropped without running, and a
+    /// file kept open for the library, such as the lock of
+    /// [`DynamicLibrary::open_locked`], is closed
+    pub fn into_raw(self) -> *mut libc::c_void {
+        let mut lib = mem::ManuallyDrop::new(self);
+        OPEN_HANDLES.fetch_sub(1, Ordering::Relaxed);
+        lib.on_close = None;
+        lib.file = None;
+        lib.path = None;
+        if lib.closed {
+            ptr::null_mut()
+        } else {
+            lib.as_raw()
+        }
+    }
+
+    /// Takes ownership of the native handle `handle`, closing it when the
+    /// returned library is closed or dropped. The flags it was opened with
+    /// are not known, so [`DynamicLibrary::open_flags`] reports the
+    /// defaults, and [`DynamicLibrary::opened_path`] is `None`. Fails if
+    /// `handle` is null, as [`DynamicLibrary::into_raw`] returns for a
+    /// closed handle
+    ///
+    /// # Safety
+    ///
+    /// `handle` must be null or a handle returned by `dlopen`, such as one
+    /// from [`DynamicLibrary::into_raw`], that is not closed elsewhere
+    pub unsafe fn from_raw(handle: *mut libc::c_void) -> Result<DynamicLibrary, Error> {
+        match NonNull::new(handle.cast()) {
+            Some(handle) => Ok(DynamicLibrary::from_non_null(handle, 0, None)),
+            None => Err(Error::loader("the library handle is null")),
+        }
+    }
+
+    /// Resolves `name` as the function pointer `F`, as
+    /// [`DynamicLibrary::get_fn`] does, then leaks the library as
+    /// [`DynamicLibrary::leak`] does, so the function stays valid for the
+    /// rest of the process. The library is never unloaded; on error it is
+    /// dropped as usual instead
+    ///
+    /// # Safety
+    ///
+    /// `F` must match the actual type of the symbol
+    pub unsafe fn leak_and_get<F: FnPtr>(self, name: &str) -> Result<F, Error> {
+        let function = unsafe { self.get_fn::<F>(name)? };
+        self.leak();
+        Ok(function)
+    }
+
+    /// Opens the library at `filename`, resolves its entry point `symbol`,
+    /// runs `call` with it and closes the library again, for tools that
+    /// load a plugin only to invoke it once. Returns what `call` returns; a
+    /// failure to close the library afterwards is ignored rather than
+    /// reported or turned into a panic
+    ///
+    /// # Safety
+    ///
+    /// The entry point must be an `extern "C" fn() -> R`, and `call` must not
+    /// let it or anything obtained from the library escape
+    pub unsafe fn run_entrypoint<R>(
+        filename: &Path,
+        symbol: &str,
+        call: impl FnOnce(extern "C" fn() -> R) -> R,
+    ) -> Result<R, Error> {
+        let mut lib = DynamicLibrary::open(Some(filename))?;
+        let entrypoint = unsafe { lib.get_fn::<extern "C" fn() -> R>(symbol)? };
+        let result = call(entrypoint);
+        let _ = lib.close();
+        Ok(result)
+    }
+
+    /// Fails with [`Error::NotLocal`] if the library was opened with global
+    /// scope, so its symbols may satisfy lookups made by other libraries.
+    /// A defensive check for hosts that mean to keep plugins isolated
+    pub const fn require_local(&self) -> Result<(), Error> {
+        if self.flags & dl::GLOBAL != 0 {
+            Err(Error::NotLocal)
+        } else {
+            Ok(())
         }
     }
 
@@ -61,11 +895,47 @@ impl DynamicLibrary {
     pub fn prepend_search_path(path: &Path) {
         let mut search_path = DynamicLibrary::search_path();
         search_path.insert(0, path.to_path_buf());
+        DynamicLibrary::set_search_path(&search_path);
+    }
+
+    /// Prepends the canonical form of `path` to this process's search path
+    /// unless an entry naming the same directory is already there, so a
+    /// plugin directory prepended before every load is only added once
+    pub fn prepend_search_path_unique(path: &Path) {
+        let path = canonical_entry(path);
+        let mut search_path = DynamicLibrary::search_path();
+        if search_path
+            .iter()
+            .any(|entry| canonical_entry(entry) == path)
+        {
+            return;
+        }
+        search_path.insert(0, path);
+        DynamicLibrary::set_search_path(&search_path);
+    }
+
+    /// Removes the entries of this process's search path naming a directory
+    /// already named by an earlier entry, keeping the first one spelled as
+    /// it was. Does nothing if the search path is unset
+    pub fn dedup_search_path() {
+        let search_path = DynamicLibrary::search_path();
+        if search_path.is_empty() {
+            return;
+        }
+        let mut seen = HashSet::new();
+        let deduped: Vec<PathBuf> = search_path
+            .into_iter()
+            .filter(|entry| seen.insert(canonical_entry(entry)))
+            .collect();
+        DynamicLibrary::set_search_path(&deduped);
+    }
+
+    fn set_search_path(search_path: &[PathBuf]) {
         // TODO: Audit that the environment access only happens in single-threaded code.
         unsafe {
             env::set_var(
                 DynamicLibrary::envvar(),
-                DynamicLibrary::create_path(&search_path),
+                DynamicLibrary::create_path(search_path),
             )
         };
     }
@@ -106,27 +976,345 @@ impl DynamicLibrary {
         }
     }
 
-    /// Access the value at the symbol of the dynamic library
+    /// Captures the current value of this process's search path, keeping the
+    /// distinction between an unset and an empty variable
+    pub fn snapshot_search_path() -> SearchPathSnapshot {
+        SearchPathSnapshot {
+            value: env::var_os(DynamicLibrary::envvar()),
+        }
+    }
+
+    /// Restores this process's search path to exactly the value captured by
+    /// [`DynamicLibrary::snapshot_search_path`], unsetting it if it was unset
+    pub fn restore_search_path(snapshot: SearchPathSnapshot) {
+        // TODO: Audit that the environment access only happens in single-threaded code.
+        unsafe {
+            match snapshot.value {
+                Some(value) => env::set_var(DynamicLibrary::envvar(), value),
+                None => env::remove_var(DynamicLibrary::envvar()),
+            }
+        };
+    }
+
+    /// Access the value at the symbol of the dynamic library, failing with
+    /// [`Error::SymbolMissing`] if the library does not define it
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn symbol<T>(&self, symbol: &str) -> Result<*mut T, Error> {
+        let raw_string = CString::new(symbol).map_err(|err| Error::InvalidSymbolName {
+            name: symbol.to_string(),
+            position: err.nul_position(),
+        })?;
+        unsafe { self.symbol_cstr(&raw_string) }
+    }
+
+    /// Same as [`DynamicLibrary::symbol`] but takes the exact bytes of the
+    /// name, which need not be UTF-8, as some mangling schemes produce. A
+    /// single trailing NUL byte is allowed; any other fails with
+    /// [`Error::InvalidSymbolName`]
     #[allow(clippy::missing_safety_doc)]
-    pub unsafe fn symbol<T>(&self, symbol: &str) -> Result<*mut T, String> {
+    pub unsafe fn symbol_bytes<T>(&self, symbol: &[u8]) -> Result<*mut T, Error> {
+        let name = symbol.strip_suffix(b"\0").unwrap_or(symbol);
+        let raw_string = CString::new(name).map_err(|err| Error::InvalidSymbolName {
+            name: String::from_utf8_lossy(name).into_owned(),
+            position: err.nul_position(),
+        })?;
+        unsafe { self.symbol_cstr(&raw_string) }
+    }
+
+    /// [`Error::SymbolMissing`] for `name`, naming this library
+    pub(crate) fn symbol_missing(&self, name: &str) -> Error {
+        Error::SymbolMissing {
+            name: name.to_string(),
+            library: self.path().map(Path::to_path_buf),
+        }
+    }
+
+    unsafe fn symbol_cstr<T>(&self, raw_string: &CStr) -> Result<*mut T, Error> {
         unsafe {
             // This function should have a lifetime constraint of 'a on
             // T but that feature is still unimplemented
 
-            let Ok(raw_string) = CString::new(symbol) else {
-                return Err(format!("failed to access `{symbol}`"));
-            };
+            if self.closed {
+                return Err(Error::HandleClosed);
+            }
             let maybe_symbol_value =
-                dl::check_for_errors_in(|| dl::symbol(self.handle, raw_string.as_ptr()));
+                dl::check_for_errors_in(|| dl::symbol(self.handle.as_ptr(), raw_string.as_ptr()));
 
             // The value must not be constructed if there is an error so
-            // the destructor does not run.
+            // the destructor does not run. dlsym on an open handle only
+            // fails when the symbol is not defined
             match maybe_symbol_value {
+                Err(Error::Loader { .. }) => {
+                    Err(self.symbol_missing(&raw_string.to_string_lossy()))
+                }
                 Err(err) => Err(err),
+                #[cfg(target_os = "linux")]
+                Ok(symbol_value)
+                    if self.main_only
+                        && dl::containing_object(symbol_value) != Some(self.handle.as_ptr()) =>
+                {
+                    Err(Error::OutsideScope(
+                        raw_string.to_string_lossy().into_owned(),
+                    ))
+                }
                 Ok(symbol_value) => Ok(mem::transmute::<*mut u8, *mut T>(symbol_value)),
             }
         }
     }
+
+    /// Copies the value of type `T` stored at the exported object `name`,
+    /// such as a configuration struct, unlike [`DynamicLibrary::symbol`]
+    /// which returns its address. Fails with [`Error::NullSymbol`] if the
+    /// export's value is null.
+    ///
+    /// # Safety
+    ///
+    /// The object must be at least `size_of::<T>()` bytes long and hold a
+    /// valid `T`, laid out as `T` is (`#[repr(C)]` for C structs). It is read
+    /// with [`ptr::read_unaligned`] so it need not be aligned for `T`
+    pub unsafe fn read_symbol<T: Copy>(&self, name: &str) -> Result<T, Error> {
+        let address = unsafe { self.symbol::<T>(name)? };
+        if address.is_null() {
+            return Err(Error::NullSymbol {
+                name: name.to_string(),
+            });
+        }
+        Ok(unsafe { ptr::read_unaligned(address) })
+    }
+
+    /// Borrows the exported object `name` in place as a pinned `T`, for
+    /// objects such as self-referential tables that must not be moved. The
+    /// reference cannot outlive the borrow of the library. Fails with
+    /// [`Error::NullSymbol`] if the export's value is null.
+    ///
+    /// # Safety
+    ///
+    /// The object must hold a valid `T`, suitably aligned, that nothing
+    /// writes to while the reference is alive. Pinning holds because the
+    /// object lives in the mapped image, which stays in place while the
+    /// library is open; it must not be unloaded by other means meanwhile
+    pub unsafe fn symbol_pinned<T>(&self, name: &str) -> Result<Pin<&T>, Error> {
+        let address = unsafe { self.symbol::<T>(name)? };
+        if address.is_null() {
+            return Err(Error::NullSymbol {
+                name: name.to_string(),
+            });
+        }
+        Ok(unsafe { Pin::new_unchecked(&*address) })
+    }
+
+    /// Access the value at the symbol `name` of the given `version`, such as
+    /// `GLIBC_2.2.5`, rather than its default version. Errors name the
+    /// symbol as `name@version`
+    #[cfg(target_os = "linux")]
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn versioned_symbol<T>(&self, name: &str, version: &str) -> Result<*mut T, Error> {
+        if self.closed {
+            return Err(Error::HandleClosed);
+        }
+        let qualified = || format!("{name}@{version}");
+        let raw_name = CString::new(name).map_err(|err| Error::InvalidSymbolName {
+            name: qualified(),
+            position: err.nul_position(),
+        })?;
+        let raw_version = CString::new(version).map_err(|err| Error::InvalidSymbolName {
+            name: qualified(),
+            position: name.len() + 1 + err.nul_position(),
+        })?;
+        // dlvsym on an open handle only fails when the symbol is not
+        // defined under that version
+        match dl::check_for_errors_in(|| unsafe {
+            dl::versioned_symbol(
+                self.handle.as_ptr(),
+                raw_name.as_ptr(),
+                raw_version.as_ptr(),
+            )
+        }) {
+            Ok(symbol_value) => Ok(symbol_value.cast::<T>()),
+            Err(Error::Loader { .. }) => Err(self.symbol_missing(&qualified())),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Same as [`DynamicLibrary::versioned_symbol`] but returns `Ok(None)`
+    /// when the library does not define `name` under `version`, as older
+    /// releases of a library may not
+    #[cfg(target_os = "linux")]
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn versioned_symbol_optional<T>(
+        &self,
+        name: &str,
+        version: &str,
+    ) -> Result<Option<*mut T>, Error> {
+        match unsafe { self.versioned_symbol(name, version) } {
+            Ok(symbol_value) => Ok(Some(symbol_value)),
+            Err(Error::SymbolMissing { .. }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Access the value at the symbol `name` as defined by this library
+    /// itself, failing with [`Error::OutsideScope`] when the lookup through
+    /// the handle finds it in one of the library's dependencies instead.
+    ///
+    /// Lookups through the handle never consult the global scope, so the
+    /// result does not depend on which other libraries were opened with
+    /// [`OpenOptions::global`]. The reverse does not hold: the loader offers
+    /// no way to take a library opened with global scope out of it, so its
+    /// symbols keep satisfying the lookups of libraries loaded later
+    #[cfg(target_os = "linux")]
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn scoped_symbol<T>(&self, name: &str) -> Result<*mut T, Error> {
+        let symbol_value = unsafe { self.symbol::<T>(name)? };
+        if unsafe { dl::containing_object(symbol_value.cast()) } != Some(self.handle.as_ptr()) {
+            return Err(Error::OutsideScope(name.to_string()));
+        }
+        Ok(symbol_value)
+    }
+
+    /// Access the value at the first of `names` that resolves, trying them in
+    /// order. Useful when a symbol was renamed across library versions
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn symbol_any<T>(&self, names: &[&str]) -> Result<*mut T, Error> {
+        let mut failures = Vec::with_capacity(names.len());
+        for name in names {
+            match unsafe { self.symbol(name) } {
+                Ok(symbol) => return Ok(symbol),
+                Err(Error::HandleClosed) => return Err(Error::HandleClosed),
+                Err(err) => failures.push(err),
+            }
+        }
+        Err(Error::NoCandidateResolved(failures))
+    }
+
+    /// Same as [`DynamicLibrary::symbol`] but, when the lookup fails, calls
+    /// [`DynamicLibrary::bind_now`] and looks `symbol` up once more in the
+    /// reopened library.
+    ///
+    /// A workaround for plugins whose lazy relocations fail intermittently,
+    /// such as while a dependency is briefly unavailable, not a guarantee:
+    /// the second lookup fails just the same when the cause persists, and
+    /// the error of the first one is returned when binding fails
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn symbol_resilient<T>(&mut self, symbol: &str) -> Result<*mut T, Error> {
+        retry_after_bind(
+            self,
+            |lib| unsafe { lib.symbol(symbol) },
+            DynamicLibrary::bind_now,
+        )
+    }
+
+    /// Access the value at the symbol `name` in the first of `handles`
+    /// defining it, looking through each handle in order, such as a
+    /// plugin's own handle and then the host's. Fails with
+    /// [`Error::NoCandidateResolved`] holding each handle's failure
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn symbol_in_chain<T>(
+        handles: &[&DynamicLibrary],
+        name: &str,
+    ) -> Result<*mut T, Error> {
+        let mut failures = Vec::with_capacity(handles.len());
+        for lib in handles {
+            match unsafe { lib.symbol(name) } {
+                Ok(symbol) => return Ok(symbol),
+                Err(err) => failures.push(err),
+            }
+        }
+        Err(Error::NoCandidateResolved(failures))
+    }
+
+    /// Resolves `names` in one pass and stores the pointers into `out`,
+    /// treating `T` as an array of pointers in field order. `out` is only
+    /// written once every name resolved.
+    ///
+    /// # Safety
+    ///
+    /// `T` must be `#[repr(C)]` and consist of exactly `names.len()` fields of
+    /// pointer type (such as `extern "C" fn` pointers), each matching the
+    /// type of the symbol named at the same index. The size is checked; the
+    /// field types are not.
+    pub unsafe fn fill_vtable<T>(&self, names: &[&str], out: &mut T) -> Result<(), Error> {
+        let expected = names.len() * mem::size_of::<*mut u8>();
+        if mem::size_of::<T>() != expected || mem::align_of::<T>() != mem::align_of::<*mut u8>() {
+            return Err(Error::LayoutMismatch {
+                expected,
+                found: mem::size_of::<T>(),
+            });
+        }
+        let mut pointers = Vec::with_capacity(names.len());
+        for name in names {
+            pointers.push(unsafe { self.symbol::<u8>(name)? });
+        }
+        let slots = (out as *mut T).cast::<*mut u8>();
+        for (i, pointer) in pointers.into_iter().enumerate() {
+            unsafe { slots.add(i).write(pointer) };
+        }
+        Ok(())
+    }
+
+    /// Resolves `name` as the pointer-sized `F`, usually a function pointer,
+    /// and stores its address into `slot` with `Release` ordering, so that
+    /// threads loading the slot with `Acquire` ordering see a usable
+    /// pointer. `slot` is left alone on error and when the symbol resolves
+    /// to null
+    ///
+    /// # Safety
+    ///
+    /// `F` must match the actual type of the symbol, and the pointer must
+    /// not be used once the library is closed
+    pub unsafe fn install_into<F: Copy>(
+        &self,
+        name: &str,
+        slot: &AtomicPtr<()>,
+    ) -> Result<(), Error> {
+        let symbol = unsafe { self.get::<F>(name.as_bytes())? };
+        if let Some(pointer) = symbol.try_as_raw_ptr() {
+            slot.store(pointer.cast(), Ordering::Release);
+        }
+        Ok(())
+    }
+}
+
+/// Runs `job` on a new thread and returns its result, or [`Error::Timeout`]
+/// if it does not finish within `timeout`. A late result is dropped on the
+/// job's thread
+fn with_timeout<T: Send + 'static>(
+    timeout: Duration,
+    job: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, Error> {
+    let (sender, receiver) = mpsc::sync_channel(1);
+    thread::Builder::new()
+        .name("dynamite-open".to_string())
+        .spawn(move || {
+            let _ = sender.send(job());
+        })
+        .map_err(|err| Error::loader(format!("failed to spawn a thread: {err}")))?;
+    receiver
+        .recv_timeout(timeout)
+        .map_err(|_| Error::Timeout(timeout))
+}
+
+/// Runs `lookup` on `target`, then `bind` and `lookup` once more if it
+/// failed with anything but [`Error::HandleClosed`]
+fn retry_after_bind<L, T>(
+    target: &mut L,
+    lookup: impl Fn(&L) -> Result<T, Error>,
+    bind: impl FnOnce(&mut L) -> Result<(), Error>,
+) -> Result<T, Error> {
+    match lookup(target) {
+        Err(Error::HandleClosed) => Err(Error::HandleClosed),
+        Err(err) => match bind(target) {
+            Ok(()) => lookup(target),
+            Err(_) => Err(err),
+        },
+        ok => ok,
+    }
+}
+
+/// The canonical form of a search path entry, or the entry itself if it
+/// cannot be canonicalized, e.g. because the directory does not exist
+fn canonical_entry(entry: &Path) -> PathBuf {
+    fs::canonicalize(entry).unwrap_or_else(|_| entry.to_path_buf())
 }
 
 #[cfg(any(
@@ -136,45 +1324,137 @@ impl DynamicLibrary {
     target_os = "ios",
     target_os = "freebsd",
     target_os = "dragonfly",
-    target_os = "openbsd"
+    target_os = "openbsd",
+    target_os = "solaris",
+    target_os = "illumos"
 ))]
 mod dl {
     use std::{
         ffi::{CStr, CString, OsStr},
+        io, mem,
         os::unix::ffi::OsStrExt,
-        ptr, str,
+        path::Path,
+        ptr::{self, NonNull},
+        str,
     };
 
-    pub fn open(filename: Option<&OsStr>) -> Result<*mut u8, String> {
+    use crate::Error;
+
+    pub fn open(filename: Option<&OsStr>, flags: libc::c_int) -> Result<*mut u8, Error> {
+        let raw_path = filename.map(path_cstring).transpose()?;
         check_for_errors_in(|| unsafe {
-            match filename {
-                Some(filename) => open_external(filename),
-                None => open_internal(),
+            match &raw_path {
+                Some(raw_path) => open_external(raw_path, flags),
+                None => open_internal(flags),
             }
         })
+        .map_err(|err| match filename {
+            Some(filename) => classify_invalid_file(Path::new(filename), err),
+            None => err,
+        })
     }
 
-    const LAZY: libc::c_int = 1;
-
-    unsafe fn open_external(filename: &OsStr) -> *mut u8 {
-        unsafe {
-            let Ok(s) = CString::new(filename.as_bytes()) else {
-                panic!("failed to open external `{}`", filename.to_string_lossy());
+    /// Turns the loader's complaints about the file `path` itself being
+    /// truncated or not a library into [`Error::InvalidLibraryFile`], leaving
+    /// those about its dependencies alone
+    fn classify_invalid_file(path: &Path, err: Error) -> Error {
+        const INVALID_FILE: &[&str] = &[
+            ": file too short",
+            ": invalid ELF header",
+            ": not a mach-o file",
+            ": truncated mach-o",
+        ];
+        let Error::Loader { message, .. } = &err else {
+            return err;
+        };
+        // The message names the object at fault first
+        let about_path = path.file_name().is_some_and(|name| {
+            message
+                .split(": ")
+                .next()
+                .is_some_and(|object| Path::new(object).file_name() == Some(name))
+        });
+        if !about_path || !INVALID_FILE.iter().any(|needle| message.contains(needle)) {
+            return err;
+        }
+        // The header check on disk tells more than the loader when the file
+        // is not an ELF object at all
+        #[cfg(target_os = "linux")]
+        if let Err(Error::MalformedElf { reason, .. }) = crate::elf::ElfFile::read_header(path) {
+            return Error::InvalidLibraryFile {
+                path: path.to_path_buf(),
+                reason,
             };
-            dlopen(s.as_ptr(), LAZY) as *mut u8
         }
+        Error::InvalidLibraryFile {
+            path: path.to_path_buf(),
+            reason: message.rsplit(": ").next().unwrap_or(message).to_string(),
+        }
+    }
+
+    pub fn open_cstr(filename: Option<&CStr>, flags: libc::c_int) -> Result<*mut u8, Error> {
+        let filename = filename.map_or(ptr::null(), CStr::as_ptr);
+        check_for_errors_in(|| unsafe { dlopen(filename, flags) as *mut u8 })
+    }
+
+    pub const LAZY: libc::c_int = libc::RTLD_LAZY;
+    pub const NOW: libc::c_int = libc::RTLD_NOW;
+    pub const GLOBAL: libc::c_int = libc::RTLD_GLOBAL;
+    pub const NODELETE: libc::c_int = libc::RTLD_NODELETE;
+
+    // Symbol search scopes specific to the Solaris runtime linker
+    #[cfg(any(target_os = "solaris", target_os = "illumos"))]
+    pub const GROUP: libc::c_int = libc::RTLD_GROUP;
+    #[cfg(any(target_os = "solaris", target_os = "illumos"))]
+    pub const WORLD: libc::c_int = libc::RTLD_WORLD;
+    #[cfg(any(target_os = "solaris", target_os = "illumos"))]
+    pub const PARENT: libc::c_int = libc::RTLD_PARENT;
+    #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+    pub const GROUP: libc::c_int = 0;
+    #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+    pub const WORLD: libc::c_int = 0;
+    #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+    pub const PARENT: libc::c_int = 0;
+
+    /// The path `filename` as passed to the loader, failing with
+    /// [`Error::InvalidCString`] if it contains a NUL byte
+    fn path_cstring(filename: &OsStr) -> Result<CString, Error> {
+        CString::new(filename.as_bytes()).map_err(|err| Error::InvalidCString {
+            path: Path::new(filename).to_path_buf(),
+            position: err.nul_position(),
+        })
+    }
+
+    unsafe fn open_external(filename: &CStr, flags: libc::c_int) -> *mut u8 {
+        unsafe { dlopen(filename.as_ptr(), flags) as *mut u8 }
     }
 
-    unsafe fn open_internal() -> *mut u8 {
-        unsafe { dlopen(ptr::null(), LAZY) as *mut u8 }
+    unsafe fn open_internal(flags: libc::c_int) -> *mut u8 {
+        unsafe { dlopen(ptr::null(), flags) as *mut u8 }
     }
 
-    pub fn check_for_errors_in<T, F>(f: F) -> Result<T, String>
+    /// Turns the handle of a successful open into a `NonNull`, failing if
+    /// the loader returned null without reporting an error, as `dlopen`
+    /// does for `RTLD_NOLOAD` when the library is not loaded
+    pub fn non_null(handle: *mut u8) -> Result<NonNull<u8>, Error> {
+        NonNull::new(handle).ok_or_else(|| Error::loader("the library is not loaded"))
+    }
+
+    pub fn check_for_errors_in<T, F>(f: F) -> Result<T, Error>
     where
         F: FnOnce() -> T,
     {
         unsafe {
+            // Both are cleared first so a stale value is not blamed on this
+            // call, as only some loaders reset the error on success, and
+            // errno is read before anything else can allocate and overwrite
+            // it. The result itself is never taken as a sign of failure:
+            // `dlsym` legitimately returns null for a symbol whose value is
+            // null
+            dlerror();
+            *errno_location() = 0;
             let result = f();
+            let errno = *errno_location();
 
             let last_error = dlerror() as *const libc::c_char;
             if last_error.is_null() {
@@ -182,19 +1462,191 @@ mod dl {
             } else {
                 let s = CStr::from_ptr(last_error).to_bytes();
                 let error = str::from_utf8(s)
-                    .map_err(|e| format!("failed to check for errors: {e}"))?
+                    .map_err(|e| Error::loader(format!("failed to check for errors: {e}")))?
                     .to_string();
-                Err(error)
+                let raw_os_error = match errno {
+                    0 => errno_in_message(&error),
+                    errno => Some(errno),
+                };
+                Err(Error::Loader {
+                    message: error,
+                    raw_os_error,
+                })
             }
         }
     }
 
-    pub unsafe fn symbol(handle: *mut u8, symbol: *const libc::c_char) -> *mut u8 {
-        unsafe { dlsym(handle as *mut libc::c_void, symbol) as *mut u8 }
+    /// glibc restores `errno` before `dlopen` returns but ends the message
+    /// with the description of the code, so the code is recovered from that
+    fn errno_in_message(message: &str) -> Option<i32> {
+        (1..256).find(|&code| {
+            let description = io::Error::from_raw_os_error(code).to_string();
+            description
+                .strip_suffix(&format!(" (os error {code})"))
+                .is_some_and(|description| message.ends_with(&format!(": {description}")))
+        })
     }
-    pub unsafe fn close(handle: *mut u8) {
-        unsafe {
-            dlclose(handle as *mut libc::c_void);
+
+    #[cfg(any(target_os = "linux", target_os = "dragonfly"))]
+    unsafe fn errno_location() -> *mut libc::c_int {
+        unsafe { libc::__errno_location() }
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+    unsafe fn errno_location() -> *mut libc::c_int {
+        unsafe { libc::__error() }
+    }
+
+    #[cfg(target_os = "openbsd")]
+    unsafe fn errno_location() -> *mut libc::c_int {
+        unsafe { libc::__errno() }
+    }
+
+    #[cfg(any(target_os = "solaris", target_os = "illumos"))]
+    unsafe fn errno_location() -> *mut libc::c_int {
+        unsafe { libc::___errno() }
+    }
+
+    pub unsafe fn symbol(handle: *mut u8, symbol: *const libc::c_char) -> *mut u8 {
+        unsafe { dlsym(handle as *mut libc::c_void, symbol) as *mut u8 }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub unsafe fn versioned_symbol(
+        handle: *mut u8,
+        symbol: *const libc::c_char,
+        version: *const libc::c_char,
+    ) -> *mut u8 {
+        unsafe { dlvsym(handle as *mut libc::c_void, symbol, version) as *mut u8 }
+    }
+    /// Returns the handle of the loaded object whose mappings contain `addr`
+    #[cfg(target_os = "linux")]
+    pub unsafe fn containing_object(addr: *const u8) -> Option<*mut u8> {
+        unsafe {
+            let mut info = mem::zeroed::<libc::Dl_info>();
+            let mut map = ptr::null_mut();
+            if dladdr1(
+                addr as *const libc::c_void,
+                &mut info,
+                &mut map,
+                RTLD_DL_LINKMAP,
+            ) == 0
+            {
+                None
+            } else {
+                Some(map as *mut u8)
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    const RTLD_DL_LINKMAP: libc::c_int = 2;
+
+    /// Returns the loader's link map entry for `handle`
+    #[cfg(target_os = "linux")]
+    pub fn link_map(handle: *mut u8) -> Result<*mut u8, Error> {
+        let mut map: *mut libc::c_void = ptr::null_mut();
+        let result = check_for_errors_in(|| unsafe {
+            dlinfo(
+                handle as *mut libc::c_void,
+                libc::RTLD_DI_LINKMAP,
+                (&mut map as *mut *mut libc::c_void).cast(),
+            )
+        })?;
+        if result != 0 || map.is_null() {
+            return Err(Error::loader("failed to query the link map"));
+        }
+        Ok(map as *mut u8)
+    }
+
+    /// Opens `filename` in the link-map namespace `namespace`, or in a new
+    /// one for `None`, returning the handle and the namespace it is in
+    #[cfg(target_os = "linux")]
+    pub fn open_in_namespace(
+        namespace: Option<libc::Lmid_t>,
+        filename: &OsStr,
+        flags: libc::c_int,
+    ) -> Result<(*mut u8, libc::Lmid_t), Error> {
+        let raw_path = path_cstring(filename)?;
+        let namespace = namespace.unwrap_or(libc::LM_ID_NEWLM);
+        let handle = check_for_errors_in(|| unsafe {
+            libc::dlmopen(namespace, raw_path.as_ptr(), flags) as *mut u8
+        })
+        .map_err(|err| classify_invalid_file(Path::new(filename), err))?;
+        let mut opened_in: libc::Lmid_t = 0;
+        let result = check_for_errors_in(|| unsafe {
+            dlinfo(
+                handle as *mut libc::c_void,
+                libc::RTLD_DI_LMID,
+                (&mut opened_in as *mut libc::Lmid_t).cast(),
+            )
+        });
+        match result {
+            Ok(0) => Ok((handle, opened_in)),
+            _ => {
+                unsafe { close(handle) };
+                Err(Error::loader(
+                    "failed to query the namespace of the library",
+                ))
+            }
+        }
+    }
+
+    /// `Dl_serpath`
+    #[cfg(target_os = "linux")]
+    #[repr(C)]
+    struct SearchDir {
+        name: *const libc::c_char,
+        flags: libc::c_uint,
+    }
+
+    /// The header of `Dl_serinfo`, followed by `count` [`SearchDir`]s and
+    /// the strings they point to
+    #[cfg(target_os = "linux")]
+    #[repr(C)]
+    struct SearchInfo {
+        size: libc::size_t,
+        count: libc::c_uint,
+    }
+
+    /// Returns the directories the loader searches for the dependencies of
+    /// `handle`, in order
+    #[cfg(target_os = "linux")]
+    pub fn search_dirs(handle: *mut u8) -> Result<Vec<std::path::PathBuf>, Error> {
+        let mut header = SearchInfo { size: 0, count: 0 };
+        check_for_errors_in(|| unsafe {
+            dlinfo(
+                handle as *mut libc::c_void,
+                libc::RTLD_DI_SERINFOSIZE,
+                (&mut header as *mut SearchInfo).cast(),
+            )
+        })?;
+        // The second call fills a buffer of the size reported by the first,
+        // whose header must be passed back as it was returned
+        let words = header.size.div_ceil(mem::size_of::<usize>());
+        let mut buffer = vec![0usize; words.max(2)];
+        let info = buffer.as_mut_ptr().cast::<SearchInfo>();
+        unsafe { info.write(header) };
+        check_for_errors_in(|| unsafe {
+            dlinfo(
+                handle as *mut libc::c_void,
+                libc::RTLD_DI_SERINFO,
+                info.cast(),
+            )
+        })?;
+        unsafe {
+            let dirs = info.add(1).cast::<SearchDir>();
+            Ok((0..(*info).count as usize)
+                .map(|i| (*dirs.add(i)).name)
+                .filter(|name| !name.is_null())
+                .map(|name| OsStr::from_bytes(CStr::from_ptr(name).to_bytes()).into())
+                .collect())
+        }
+    }
+
+    pub unsafe fn close(handle: *mut u8) {
+        unsafe {
+            dlclose(handle as *mut libc::c_void);
         }
     }
 
@@ -203,14 +1655,52 @@ mod dl {
         fn dlerror() -> *mut libc::c_char;
         fn dlsym(handle: *mut libc::c_void, symbol: *const libc::c_char) -> *mut libc::c_void;
         fn dlclose(handle: *mut libc::c_void) -> libc::c_int;
+        #[cfg(target_os = "linux")]
+        fn dlvsym(
+            handle: *mut libc::c_void,
+            symbol: *const libc::c_char,
+            version: *const libc::c_char,
+        ) -> *mut libc::c_void;
+        #[cfg(target_os = "linux")]
+        fn dlinfo(
+            handle: *mut libc::c_void,
+            request: libc::c_int,
+            info: *mut libc::c_void,
+        ) -> libc::c_int;
+        #[cfg(target_os = "linux")]
+        fn dladdr1(
+            addr: *const libc::c_void,
+            info: *mut libc::Dl_info,
+            extra_info: *mut *mut libc::c_void,
+            flags: libc::c_int,
+        ) -> libc::c_int;
     }
 }
 
 #[cfg(all(test, not(target_os = "ios")))]
 mod test {
-    use std::{mem, path::Path};
+    use std::{
+        fs, io, mem,
+        os::unix::{ffi::OsStrExt, fs::PermissionsExt},
+        path::Path,
+        sync::atomic::{AtomicBool, AtomicI32, Ordering},
+        time::Duration,
+    };
 
     use super::*;
+    use crate::test_support::{self, ENV_LOCK};
+
+    /// Opens a library exporting the C math functions
+    fn libm() -> DynamicLibrary {
+        #[cfg(target_os = "linux")]
+        let path = Some(Path::new("libm.so.6"));
+        #[cfg(not(target_os = "linux"))]
+        let path = None;
+        match DynamicLibrary::open(path) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(libm) => libm,
+        }
+    }
 
     #[test]
     #[cfg_attr(target_os = "linux", ignore)]
@@ -256,4 +1746,1110 @@ mod test {
             Ok(_) => panic!("Successfully opened the empty library."),
         }
     }
+
+    #[test]
+    fn test_open_path_with_nul_byte() {
+        let path = Path::new("libdynamite\0nul.so");
+        match DynamicLibrary::open(Some(path)) {
+            Err(Error::InvalidCString { position, .. }) => assert_eq!(position, 11),
+            Err(error) => panic!("Expected an invalid C string, got: {}", error),
+            Ok(_) => panic!("Opened a path with a NUL byte."),
+        }
+        #[cfg(target_os = "linux")]
+        match DynamicLibrary::open_in_new_namespace(path) {
+            Err(Error::InvalidCString { position, .. }) => assert_eq!(position, 11),
+            Err(error) => panic!("Expected an invalid C string, got: {}", error),
+            Ok(_) => panic!("Opened a path with a NUL byte in a new namespace."),
+        }
+    }
+
+    #[test]
+    fn test_symbol_after_close_is_handle_closed() {
+        let mut lib = match DynamicLibrary::open(None) {
+            Err(error) => panic!("Could not load self as module: {}", error),
+            Ok(lib) => lib,
+        };
+        if let Err(error) = lib.close() {
+            panic!("Could not close self: {}", error);
+        }
+
+        match unsafe { lib.symbol::<u8>("malloc") } {
+            Err(Error::HandleClosed) => {}
+            Err(error) => panic!("Expected a closed handle error, got: {}", error),
+            Ok(_) => panic!("Resolved a symbol through a closed handle."),
+        }
+        assert_eq!(lib.close(), Err(Error::HandleClosed));
+    }
+
+    #[test]
+    fn test_cross_thread_lookups() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<DynamicLibrary>();
+
+        let path = test_support::build_library(
+            "cross_thread",
+            "int thread_square(int x) { return x * x; }",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        thread::scope(|scope| {
+            for i in 0..8 {
+                let lib = &lib;
+                scope.spawn(move || {
+                    for _ in 0..100 {
+                        // Interleaved failures must not leak into the
+                        // lookups of other threads
+                        assert!(unsafe { lib.symbol::<u8>("thread_missing") }.is_err());
+                        let square = match unsafe { lib.symbol::<u8>("thread_square") } {
+                            Err(error) => panic!("Could not find thread_square: {}", error),
+                            Ok(symbol) => unsafe {
+                                mem::transmute::<*mut u8, extern "C" fn(i32) -> i32>(symbol)
+                            },
+                        };
+                        assert_eq!(square(i), i * i);
+                    }
+                });
+            }
+        });
+        let closed = thread::spawn(move || {
+            let mut lib = lib;
+            lib.close()
+        });
+        assert!(matches!(closed.join(), Ok(Ok(()))));
+    }
+
+    #[test]
+    fn test_debug_shows_path() {
+        let path = test_support::build_library("debugged", "int debugged(void) { return 0; }", &[]);
+        let mut lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(lib.path(), Some(path.as_path()));
+        assert_eq!(lib.to_string(), path.display().to_string());
+        let debug = format!("{lib:?}");
+        assert!(debug.starts_with("DynamicLibrary { path: Some("));
+        assert!(debug.contains(&format!("{:?}", path)));
+        assert!(debug.contains("closed: false"));
+        match unsafe { lib.symbol::<u8>("not_debugged") } {
+            Err(error) => assert!(error.to_string().contains(&path.display().to_string())),
+            Ok(_) => panic!("Resolved a symbol the fixture does not define."),
+        }
+        if let Err(error) = lib.close() {
+            panic!("Could not close the fixture: {}", error);
+        }
+        assert!(format!("{lib:?}").contains("closed: true"));
+
+        match DynamicLibrary::open(None) {
+            Err(error) => panic!("Could not load self as module: {}", error),
+            Ok(lib) => {
+                assert_eq!(lib.path(), Some(Path::new("self")));
+                assert_eq!(lib.to_string(), "self");
+                assert!(format!("{lib:?}").contains("path: Some(\"self\")"));
+            }
+        }
+        #[cfg(target_os = "linux")]
+        match DynamicLibrary::open_cstr(Some(c"libm.so.6")) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(lib) => assert_eq!(lib.to_string(), "<unknown>"),
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_null_valued_symbol() {
+        // An absolute symbol has the value it is set to, unrelocated, so
+        // `dlsym` returns null for it without reporting an error
+        let path = test_support::build_library(
+            "null_valued",
+            "__asm__(\".globl null_valued\\n.set null_valued, 0\");",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        for _ in 0..2 {
+            assert!(unsafe { lib.symbol::<u8>("null_missing") }.is_err());
+            assert_eq!(
+                unsafe { lib.symbol::<u8>("null_valued") },
+                Ok(ptr::null_mut())
+            );
+        }
+        assert!(matches!(
+            unsafe { lib.get::<*mut u8>(b"null_valued") },
+            Err(Error::NullSymbol { name }) if name == "null_valued"
+        ));
+    }
+
+    #[test]
+    fn test_restore_search_path_is_exact() {
+        let _guard = ENV_LOCK.lock();
+        let original = DynamicLibrary::snapshot_search_path();
+
+        let value = std::ffi::OsStr::from_bytes(b"/opt/a::/opt/\xffb:");
+        unsafe { env::set_var(DynamicLibrary::envvar(), value) };
+        let snapshot = DynamicLibrary::snapshot_search_path();
+        DynamicLibrary::prepend_search_path(Path::new("/opt/c"));
+        unsafe { env::set_var(DynamicLibrary::envvar(), "") };
+        unsafe { env::remove_var(DynamicLibrary::envvar()) };
+        DynamicLibrary::prepend_search_path(Path::new("/opt/d"));
+        DynamicLibrary::restore_search_path(snapshot);
+        assert_eq!(
+            env::var_os(DynamicLibrary::envvar()).as_deref(),
+            Some(value)
+        );
+
+        unsafe { env::remove_var(DynamicLibrary::envvar()) };
+        let snapshot = DynamicLibrary::snapshot_search_path();
+        DynamicLibrary::prepend_search_path(Path::new("/opt/e"));
+        DynamicLibrary::restore_search_path(snapshot);
+        assert_eq!(env::var_os(DynamicLibrary::envvar()), None);
+
+        DynamicLibrary::restore_search_path(original);
+    }
+
+    #[test]
+    fn test_open_relative_to() {
+        let path = test_support::build_library(
+            "open_relative_to",
+            "int relative(void) { return 1; }",
+            &[],
+        );
+        let name = DynamicLibrary::library_filename("open_relative_to");
+        let name = Path::new(&name);
+        let cwd = env::current_dir().ok();
+        for dir in [
+            test_support::fixture_dir(),
+            PathBuf::from("target/fixtures"),
+        ] {
+            match DynamicLibrary::open_relative_to(&dir, name) {
+                Err(error) => panic!("Could not open relative to {}: {}", dir.display(), error),
+                Ok(lib) => {
+                    assert!(lib.opened_path().is_some_and(Path::is_absolute));
+                    assert!(unsafe { lib.symbol::<u8>("relative") }.is_ok());
+                }
+            }
+        }
+        assert_eq!(env::current_dir().ok(), cwd);
+        assert!(DynamicLibrary::open_relative_to(Path::new("/does/not/exist"), name).is_err());
+        assert!(path.ends_with(name));
+    }
+
+    #[test]
+    fn test_run_entrypoint() {
+        let path =
+            test_support::build_library("run_entrypoint", "int entry(void) { return 17; }", &[]);
+        let result = unsafe { DynamicLibrary::run_entrypoint(&path, "entry", |entry| entry() + 1) };
+        assert_eq!(result, Ok(18));
+        assert!(
+            unsafe { DynamicLibrary::run_entrypoint::<i32>(&path, "missing", |entry| entry()) }
+                .is_err()
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_open_in_new_namespace_with_preload() {
+        let dir = test_support::fixture_dir().join("namespace_preload");
+        let build = |subdir: &str, name: &str, source: &str, args: &[&str]| {
+            test_support::build_library_in(&dir.join(subdir), name, source, args)
+        };
+        let stub = build(
+            "stubs",
+            "getpid_stub",
+            "int getpid(void) { return -42; }",
+            &[],
+        );
+        build(
+            "system",
+            "nsdep",
+            "int dependency_version(void) { return 1; }",
+            &["-Wl,-soname,libnsdep.so"],
+        );
+        let replacement = build(
+            "replacement",
+            "nsdep",
+            "int dependency_version(void) { return 2; }",
+            &["-Wl,-soname,libnsdep.so"],
+        );
+        let system_dir = format!("{}", dir.join("system").display());
+        let plugin = build(
+            "plugin",
+            "sandboxed",
+            "#include <unistd.h>\n\
+             int dependency_version(void);\n\
+             int sandboxed_pid(void) { return getpid(); }\n\
+             int sandboxed_dependency(void) { return dependency_version(); }\n",
+            &[
+                &format!("-L{system_dir}"),
+                &format!("-Wl,-rpath,{system_dir}"),
+                "-lnsdep",
+            ],
+        );
+        let call = |lib: &DynamicLibrary, name: &str| match unsafe { lib.symbol::<u8>(name) } {
+            Err(error) => panic!("Could not find {}: {}", name, error),
+            Ok(symbol) => unsafe { mem::transmute::<*mut u8, extern "C" fn() -> i32>(symbol)() },
+        };
+
+        match DynamicLibrary::open_in_new_namespace(&plugin) {
+            Err(error) => panic!("Could not open the plugin: {}", error),
+            Ok(lib) => {
+                assert_eq!(call(&lib, "sandboxed_pid"), std::process::id() as i32);
+                assert_eq!(call(&lib, "sandboxed_dependency"), 1);
+            }
+        }
+        match DynamicLibrary::open_in_new_namespace_with_preload(&plugin, &[&stub, &replacement]) {
+            Err(error) => panic!("Could not open the sandboxed plugin: {}", error),
+            Ok(lib) => {
+                assert_eq!(call(&lib, "sandboxed_pid"), -42);
+                assert_eq!(call(&lib, "sandboxed_dependency"), 2);
+                assert_eq!(lib.opened_path(), None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_open_secure() {
+        let dir = test_support::fixture_dir().join("secure");
+        let path = test_support::build_library_in(
+            &dir,
+            "open_secure",
+            "int secure(void) { return 1; }",
+            &[],
+        );
+        let chmod = |path: &Path, mode: u32| {
+            if let Err(error) = fs::set_permissions(path, fs::Permissions::from_mode(mode)) {
+                panic!("Could not change the mode of {}: {}", path.display(), error);
+            }
+        };
+        chmod(&dir, 0o755);
+        chmod(&path, 0o755);
+        if let Err(error) = DynamicLibrary::open_secure(&path) {
+            panic!("Could not open a safe library: {}", error);
+        }
+
+        chmod(&path, 0o775);
+        match DynamicLibrary::open_secure(&path) {
+            Err(Error::InsecurePermissions { path: rejected, .. }) => assert_eq!(rejected, path),
+            Err(error) => panic!("Unexpected error: {}", error),
+            Ok(_) => panic!("Opened a group-writable library"),
+        }
+        chmod(&path, 0o755);
+        chmod(&dir, 0o777);
+        match DynamicLibrary::open_secure(&path) {
+            Err(Error::InsecurePermissions { path: rejected, .. }) => assert_eq!(rejected, dir),
+            Err(error) => panic!("Unexpected error: {}", error),
+            Ok(_) => panic!("Opened a library in a world-writable directory"),
+        }
+        chmod(&dir, 0o755);
+    }
+
+    #[test]
+    fn test_open_locked() {
+        let path =
+            test_support::build_library("open_locked", "int locked(void) { return 1; }", &[]);
+        let try_exclusive = || {
+            let file = match fs::File::open(&path) {
+                Err(error) => panic!("Could not open {}: {}", path.display(), error),
+                Ok(file) => file,
+            };
+            unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) == 0 }
+        };
+        let mut lib = match DynamicLibrary::open_locked(&path) {
+            Err(error) => panic!("Could not open the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(lib.opened_path(), Some(path.as_path()));
+        assert!(unsafe { lib.symbol::<u8>("locked") }.is_ok());
+        assert!(!try_exclusive());
+        assert_eq!(lib.reopen(), Ok(()));
+        assert!(!try_exclusive());
+        assert!(unsafe { lib.symbol::<u8>("locked") }.is_ok());
+        assert_eq!(lib.close(), Ok(()));
+        assert!(try_exclusive());
+
+        let lib = match DynamicLibrary::open_locked(&path) {
+            Err(error) => panic!("Could not open the fixture again: {}", error),
+            Ok(lib) => lib,
+        };
+        assert!(!try_exclusive());
+        drop(lib);
+        assert!(try_exclusive());
+    }
+
+    #[test]
+    fn test_search_path_dedup() {
+        let _guard = ENV_LOCK.lock();
+        let original = DynamicLibrary::snapshot_search_path();
+        let dir = test_support::fixture_dir();
+        let canonical = match fs::canonicalize(&dir) {
+            Err(error) => panic!("Could not canonicalize the fixture dir: {}", error),
+            Ok(canonical) => canonical,
+        };
+
+        unsafe { env::set_var(DynamicLibrary::envvar(), "/opt/a") };
+        DynamicLibrary::prepend_search_path_unique(&dir);
+        DynamicLibrary::prepend_search_path_unique(&dir);
+        DynamicLibrary::prepend_search_path_unique(&dir.join("."));
+        assert_eq!(
+            DynamicLibrary::search_path(),
+            [canonical, PathBuf::from("/opt/a")]
+        );
+
+        unsafe {
+            env::set_var(
+                DynamicLibrary::envvar(),
+                "/opt/b:/opt/a:/opt/b:/opt/c:/opt/a",
+            )
+        };
+        DynamicLibrary::dedup_search_path();
+        assert_eq!(
+            DynamicLibrary::search_path(),
+            ["/opt/b", "/opt/a", "/opt/c"].map(PathBuf::from)
+        );
+
+        DynamicLibrary::restore_search_path(original);
+    }
+
+    #[test]
+    fn test_symbol_any_falls_back() {
+        let libm = libm();
+        let cosine: extern "C" fn(f64) -> f64 = unsafe {
+            match libm.symbol_any(&["does_not_exist", "cos"]) {
+                Err(error) => panic!("Could not load function cos: {}", error),
+                Ok(cosine) => mem::transmute::<*mut u8, extern "C" fn(f64) -> f64>(cosine),
+            }
+        };
+        assert_eq!(cosine(0.0), 1.0);
+
+        match unsafe { libm.symbol_any::<u8>(&["does_not_exist", "also_missing"]) } {
+            Err(Error::NoCandidateResolved(failures)) => assert_eq!(failures.len(), 2),
+            Err(error) => panic!("Expected every candidate to fail, got: {}", error),
+            Ok(_) => panic!("Resolved a symbol that does not exist."),
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_open_cstr() {
+        let libm = match DynamicLibrary::open_cstr(Some(c"libm.so.6")) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(libm) => libm,
+        };
+        if let Err(error) = unsafe { libm.symbol::<u8>("cos") } {
+            panic!("Could not load function cos: {}", error);
+        }
+
+        match DynamicLibrary::open_cstr(Some(c"/dev/null")) {
+            Err(_) => {}
+            Ok(_) => panic!("Successfully opened the empty library."),
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_require_local() {
+        let path = Path::new("libm.so.6");
+        let local = match DynamicLibrary::options().open(Some(path)) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(local.require_local(), Ok(()));
+
+        let global = match DynamicLibrary::options().global(true).open(Some(path)) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(global.require_local(), Err(Error::NotLocal));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_open_flags() {
+        let path = Path::new("libm.so.6");
+        let mut lib = match DynamicLibrary::options()
+            .now(false)
+            .global(true)
+            .open(Some(path))
+        {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(
+            lib.open_flags(),
+            *DynamicLibrary::options().now(false).global(true)
+        );
+        assert_ne!(lib.open_flags(), *DynamicLibrary::options().now(false));
+
+        assert_eq!(lib.bind_now(), Ok(()));
+        assert_eq!(
+            lib.open_flags(),
+            *DynamicLibrary::options().now(true).global(true)
+        );
+
+        match DynamicLibrary::options()
+            .now(true)
+            .global(true)
+            .open(Some(path))
+        {
+            Err(error) => panic!("Could not load the math library eagerly: {}", error),
+            Ok(eager) => assert_eq!(eager.open_flags(), lib.open_flags()),
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_process_scope() {
+        let path = test_support::build_library(
+            "process_scope",
+            "int process_scope_marker(void) { return 1; }",
+            &[],
+        );
+        let _plugin = match DynamicLibrary::options().global(true).open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+
+        let global = match DynamicLibrary::open_process(ProcessScope::Global) {
+            Err(error) => panic!("Could not load self as module: {}", error),
+            Ok(lib) => lib,
+        };
+        if let Err(error) = unsafe { global.symbol::<u8>("process_scope_marker") } {
+            panic!("Could not load the global symbol: {}", error);
+        }
+
+        let main_only = match DynamicLibrary::open_process(ProcessScope::MainOnly) {
+            Err(error) => panic!("Could not load self as module: {}", error),
+            Ok(lib) => lib,
+        };
+        match unsafe { main_only.symbol::<u8>("process_scope_marker") } {
+            Err(Error::OutsideScope(name)) => assert_eq!(name, "process_scope_marker"),
+            Err(error) => panic!("Expected an out of scope error, got: {}", error),
+            Ok(_) => panic!("Resolved a library symbol through the main executable."),
+        }
+    }
+
+    #[test]
+    fn test_symbol_in_chain() {
+        let plugin =
+            test_support::build_library("chain_plugin", "int plugin_only(void) { return 1; }", &[]);
+        let host =
+            test_support::build_library("chain_host", "int host_service(void) { return 2; }", &[]);
+        let (plugin, host) = match (
+            DynamicLibrary::open(Some(&plugin)),
+            DynamicLibrary::open(Some(&host)),
+        ) {
+            (Ok(plugin), Ok(host)) => (plugin, host),
+            (Err(error), _) | (_, Err(error)) => panic!("Could not load the fixtures: {}", error),
+        };
+
+        let found =
+            unsafe { DynamicLibrary::symbol_in_chain::<u8>(&[&plugin, &host], "host_service") };
+        assert_eq!(found, unsafe { host.symbol::<u8>("host_service") });
+        assert!(found.is_ok());
+        match unsafe { DynamicLibrary::symbol_in_chain::<u8>(&[&plugin, &host], "nowhere") } {
+            Err(Error::NoCandidateResolved(failures)) => assert_eq!(failures.len(), 2),
+            Err(error) => panic!("Expected every handle to fail, got: {}", error),
+            Ok(_) => panic!("Resolved a symbol no handle defines."),
+        }
+    }
+
+    #[test]
+    fn test_fill_vtable() {
+        #[repr(C)]
+        struct MathVTable {
+            cos: extern "C" fn(f64) -> f64,
+            sin: extern "C" fn(f64) -> f64,
+        }
+        extern "C" fn unset(_: f64) -> f64 {
+            f64::NAN
+        }
+
+        let libm = libm();
+        let mut vtable = MathVTable {
+            cos: unset,
+            sin: unset,
+        };
+        if let Err(error) = unsafe { libm.fill_vtable(&["cos", "sin"], &mut vtable) } {
+            panic!("Could not fill the vtable: {}", error);
+        }
+        assert_eq!((vtable.cos)(0.0), 1.0);
+        assert_eq!((vtable.sin)(0.0), 0.0);
+
+        match unsafe { libm.fill_vtable(&["cos"], &mut vtable) } {
+            Err(Error::LayoutMismatch { expected, found }) => assert_eq!(expected * 2, found),
+            Err(error) => panic!("Expected a layout mismatch, got: {}", error),
+            Ok(()) => panic!("Filled a vtable with the wrong number of names."),
+        }
+    }
+
+    #[test]
+    fn test_finalizer_runs_on_drop() {
+        static FINALIZED: AtomicBool = AtomicBool::new(false);
+        extern "C" fn on_shutdown() {
+            FINALIZED.store(true, Ordering::SeqCst);
+        }
+
+        let path = test_support::build_library(
+            "finalizer",
+            "void (*on_shutdown)(void);\n\
+             void plugin_shutdown(void) { if (on_shutdown) on_shutdown(); }\n\
+             __asm__(\".globl null_shutdown\\n.set null_shutdown, 0\");\n",
+            &[],
+        );
+        let lib = match unsafe { DynamicLibrary::open_with_finalizer(&path, "plugin_shutdown") } {
+            Err(error) => panic!("Could not open the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        match unsafe { lib.symbol::<extern "C" fn()>("on_shutdown") } {
+            Err(error) => panic!("Could not find the callback slot: {}", error),
+            Ok(slot) => unsafe { *slot = on_shutdown },
+        }
+        assert!(!FINALIZED.load(Ordering::SeqCst));
+        drop(lib);
+        assert!(FINALIZED.load(Ordering::SeqCst));
+
+        match unsafe { DynamicLibrary::open_with_finalizer(&path, "missing_shutdown") } {
+            Err(Error::SymbolMissing { name, library }) => {
+                assert_eq!(name, "missing_shutdown");
+                assert_eq!(library, Some(path.clone()));
+            }
+            Err(error) => panic!("Expected a missing symbol, got: {}", error),
+            Ok(_) => panic!("Opened a library without its finalizer."),
+        }
+        #[cfg(target_os = "linux")]
+        match unsafe { DynamicLibrary::open_with_finalizer(&path, "null_shutdown") } {
+            Err(Error::NullSymbol { .. }) => {}
+            Err(error) => panic!("Expected a null symbol, got: {}", error),
+            Ok(_) => panic!("Opened a library with a null finalizer."),
+        }
+    }
+
+    #[test]
+    fn test_on_close_runs_before_unload() {
+        static SEEN: AtomicI32 = AtomicI32::new(0);
+        let path =
+            test_support::build_library("on_close", "int still_loaded(void) { return 42; }", &[]);
+        let mut lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not open the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        let still_loaded = match unsafe { lib.symbol::<u8>("still_loaded") } {
+            Err(error) => panic!("Could not find still_loaded: {}", error),
+            Ok(symbol) => unsafe { mem::transmute::<*mut u8, extern "C" fn() -> i32>(symbol) },
+        };
+        lib.on_close(|| panic!("Ran a replaced close callback"));
+        lib.on_close(move || SEEN.store(still_loaded(), Ordering::SeqCst));
+        assert_eq!(SEEN.load(Ordering::SeqCst), 0);
+        drop(lib);
+        assert_eq!(SEEN.load(Ordering::SeqCst), 42);
+    }
+
+    #[test]
+    fn test_raw_os_error() {
+        match DynamicLibrary::open(Some(Path::new("/dynamite/does/not/exist.so"))) {
+            Err(error) => assert_eq!(error.raw_os_error(), Some(libc::ENOENT)),
+            Ok(_) => panic!("Successfully opened a missing library."),
+        }
+
+        // Permissions do not apply to root
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let path = test_support::fixture_dir().join("unreadable.so");
+        if let Err(error) = fs::write(&path, b"")
+            .and_then(|()| fs::set_permissions(&path, fs::Permissions::from_mode(0o000)))
+        {
+            panic!("Could not create {}: {}", path.display(), error);
+        }
+        match DynamicLibrary::open(Some(&path)) {
+            Err(error) => assert_eq!(error.raw_os_error(), Some(libc::EACCES)),
+            Ok(_) => panic!("Successfully opened an unreadable library."),
+        }
+    }
+
+    #[test]
+    fn test_into_io_error() {
+        match DynamicLibrary::open(Some(Path::new("/dynamite/does/not/exist.so"))) {
+            Err(error) => {
+                let message = error.to_string();
+                let error = io::Error::from(error);
+                assert_eq!(error.kind(), io::ErrorKind::NotFound);
+                assert_eq!(error.to_string(), message);
+            }
+            Ok(_) => panic!("Successfully opened a missing library."),
+        }
+        assert_eq!(
+            io::Error::from(Error::HandleClosed).kind(),
+            io::ErrorKind::Other
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_versioned_symbol_optional() {
+        let libm = libm();
+        match unsafe { libm.versioned_symbol_optional::<u8>("cos", "GLIBC_99.0") } {
+            Err(error) => panic!("Could not look up a missing version: {}", error),
+            Ok(symbol) => assert_eq!(symbol, None),
+        }
+        match unsafe { libm.versioned_symbol::<u8>("cos", "GLIBC_99.0") } {
+            Err(Error::SymbolMissing { name, library }) => {
+                assert_eq!(name, "cos@GLIBC_99.0");
+                assert_eq!(library.as_deref(), libm.path());
+            }
+            Err(error) => panic!("Expected a missing symbol, got: {}", error),
+            Ok(_) => panic!("Found cos@GLIBC_99.0."),
+        }
+        assert_eq!(
+            unsafe { libm.versioned_symbol_optional::<u8>("cos", "GLIBC\0_2") },
+            Err(Error::InvalidSymbolName {
+                name: "cos@GLIBC\0_2".to_string(),
+                position: 9
+            })
+        );
+        #[cfg(target_arch = "x86_64")]
+        match unsafe { libm.versioned_symbol_optional::<u8>("cos", "GLIBC_2.2.5") } {
+            Err(error) => panic!("Could not look up cos@GLIBC_2.2.5: {}", error),
+            Ok(None) => panic!("cos@GLIBC_2.2.5 is missing"),
+            Ok(Some(cos)) => {
+                let cos = unsafe { mem::transmute::<*mut u8, extern "C" fn(f64) -> f64>(cos) };
+                assert_eq!(cos(0.0), 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_library_filename() {
+        #[cfg(target_os = "linux")]
+        assert_eq!(DynamicLibrary::library_filename("demo"), "libdemo.so");
+        #[cfg(target_os = "macos")]
+        assert_eq!(DynamicLibrary::library_filename("demo"), "libdemo.dylib");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_soname_candidates() {
+        assert_eq!(
+            DynamicLibrary::soname_candidates("foo", Some(3)),
+            ["libfoo.so", "libfoo.so.3"]
+        );
+        assert_eq!(
+            DynamicLibrary::soname_candidates("foo", None),
+            ["libfoo.so"]
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn test_soname_candidates() {
+        assert_eq!(
+            DynamicLibrary::soname_candidates("foo", Some(3)),
+            ["libfoo.dylib", "libfoo.3.dylib"]
+        );
+        assert_eq!(
+            DynamicLibrary::soname_candidates("foo", None),
+            ["libfoo.dylib"]
+        );
+    }
+
+    #[test]
+    fn test_open_first_and_beside_exe() {
+        let exe_dir = match env::current_exe() {
+            Err(error) => panic!("Could not find the test executable: {}", error),
+            Ok(exe) => exe.parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+        test_support::build_library_in(
+            &exe_dir,
+            "beside_exe",
+            "int beside(void) { return 1; }",
+            &[],
+        );
+        let filename = DynamicLibrary::library_filename("beside_exe");
+        match DynamicLibrary::open_beside_exe(&filename) {
+            Err(error) => panic!(
+                "Could not open the library beside the executable: {}",
+                error
+            ),
+            Ok(lib) => assert!(unsafe { lib.symbol::<u8>("beside") }.is_ok()),
+        }
+
+        let missing = Path::new("/dynamite/does/not/exist.so");
+        match DynamicLibrary::open_first(&[missing, &exe_dir.join(&filename)]) {
+            Err(error) => panic!("Could not open the second candidate: {}", error),
+            Ok(lib) => assert!(unsafe { lib.symbol::<u8>("beside") }.is_ok()),
+        }
+        match DynamicLibrary::open_first(&[missing]) {
+            Err(Error::NoCandidateResolved(failures)) => assert_eq!(failures.len(), 1),
+            Err(error) => panic!("Expected no candidate to resolve, got: {}", error),
+            Ok(_) => panic!("Successfully opened a missing library."),
+        }
+    }
+
+    #[test]
+    fn test_read_symbol() {
+        #[repr(C)]
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Config {
+            a: libc::c_int,
+            b: libc::c_int,
+        }
+
+        let path = test_support::build_library(
+            "read_symbol",
+            "struct Config { int a; int b; };\n\
+             struct Config config = { 3, 4 };\n\
+             __asm__(\".globl null_config\\n.set null_config, 0\");\n",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        match unsafe { lib.read_symbol::<Config>("config") } {
+            Err(error) => panic!("Could not read the config: {}", error),
+            Ok(config) => assert_eq!(config, Config { a: 3, b: 4 }),
+        }
+        if unsafe { lib.read_symbol::<Config>("missing_config") }.is_ok() {
+            panic!("Read a missing symbol.");
+        }
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            unsafe { lib.read_symbol::<Config>("null_config") },
+            Err(Error::NullSymbol {
+                name: "null_config".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_symbol_pinned() {
+        #[repr(C)]
+        struct VTable {
+            add: extern "C" fn(libc::c_int, libc::c_int) -> libc::c_int,
+        }
+
+        let path = test_support::build_library(
+            "symbol_pinned",
+            "static int add(int a, int b) { return a + b; }\n\
+             struct VTable { int (*add)(int, int); };\n\
+             const struct VTable vtable = { add };\n\
+             __asm__(\".globl null_vtable\\n.set null_vtable, 0\");\n",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        match unsafe { lib.symbol_pinned::<VTable>("vtable") } {
+            Err(error) => panic!("Could not borrow the vtable: {}", error),
+            Ok(vtable) => assert_eq!((vtable.add)(2, 3), 5),
+        }
+        #[cfg(target_os = "linux")]
+        assert!(matches!(
+            unsafe { lib.symbol_pinned::<VTable>("null_vtable") },
+            Err(Error::NullSymbol { .. })
+        ));
+    }
+
+    #[test]
+    fn test_leak_and_get() {
+        let cos = match unsafe { libm().leak_and_get::<extern "C" fn(f64) -> f64>("cos") } {
+            Err(error) => panic!("Could not leak cos: {}", error),
+            Ok(cos) => cos,
+        };
+        assert_eq!(cos(0.0), 1.0);
+
+        match unsafe { libm().leak_and_get::<extern "C" fn()>("not_in_libm") } {
+            Err(Error::SymbolMissing { name, .. }) => assert_eq!(name, "not_in_libm"),
+            Err(error) => panic!("Expected a missing symbol, got: {}", error),
+            Ok(_) => panic!("Resolved a symbol libm does not define."),
+        }
+    }
+
+    #[test]
+    fn test_symbol_resilient_retries_once() {
+        // Stands in for a library whose lookups only succeed once bound
+        let mut bound = false;
+        let result = retry_after_bind(
+            &mut bound,
+            |&bound| {
+                if bound {
+                    Ok("flaky_symbol")
+                } else {
+                    Err(Error::loader("undefined symbol: flaky_dependency"))
+                }
+            },
+            |bound| {
+                *bound = true;
+                Ok(())
+            },
+        );
+        assert_eq!(result, Ok("flaky_symbol"));
+        assert!(bound);
+
+        let path = test_support::build_library(
+            "bind_now",
+            "void missing_function(void);\n\
+             void call_missing(void) { missing_function(); }\n",
+            &[],
+        );
+        match DynamicLibrary::options().now(false).open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture lazily: {}", error),
+            Ok(mut lib) => match lib.bind_now() {
+                Err(error) => assert!(error.to_string().contains("missing_function")),
+                Ok(()) => panic!("Bound a library with a missing symbol."),
+            },
+        }
+
+        let mut lib = libm();
+        match unsafe { lib.symbol_resilient::<u8>("cos") } {
+            Err(error) => panic!("Could not load function cos: {}", error),
+            Ok(cos) => assert!(!cos.is_null()),
+        }
+        match unsafe { lib.symbol_resilient::<u8>("not_a_math_function") } {
+            Err(Error::SymbolMissing { name, .. }) => assert_eq!(name, "not_a_math_function"),
+            Err(error) => panic!("Expected a missing symbol, got: {}", error),
+            Ok(_) => panic!("Resolved a symbol libm does not define."),
+        }
+    }
+
+    #[test]
+    fn test_open_shadow_copy() {
+        let source = |version: i32| format!("int shadow_generation(void) {{ return {version}; }}");
+        let path = test_support::build_library("shadow", &source(1), &[]);
+        let generation = |lib: &DynamicLibrary| match unsafe {
+            lib.symbol::<u8>("shadow_generation")
+        } {
+            Err(error) => panic!("Could not find shadow_generation: {}", error),
+            Ok(symbol) => unsafe { mem::transmute::<*mut u8, extern "C" fn() -> i32>(symbol)() },
+        };
+        let first = match DynamicLibrary::open_shadow_copy(&path) {
+            Err(error) => panic!("Could not load a shadow copy: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(first.opened_path(), Some(path.as_path()));
+
+        test_support::build_library("shadow", &source(2), &[]);
+        let second = match DynamicLibrary::open_shadow_copy(&path) {
+            Err(error) => panic!("Could not load the overwritten library: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(generation(&first), 1);
+        assert_eq!(generation(&second), 2);
+    }
+
+    #[test]
+    fn test_invalid_library_file() {
+        let library =
+            test_support::build_library("truncated", "int truncated(void) { return 1; }", &[]);
+        let dir = test_support::fixture_dir();
+        let truncated = dir.join(format!("libtruncated_copy{}", env::consts::DLL_SUFFIX));
+        let text = dir.join(format!("libtext{}", env::consts::DLL_SUFFIX));
+        let written = fs::read(&library)
+            .and_then(|contents| fs::write(&truncated, &contents[..40]))
+            .and_then(|()| fs::write(&text, "not a shared object\n"));
+        if let Err(error) = written {
+            panic!("Could not write the invalid libraries: {}", error);
+        }
+
+        for path in [&truncated, &text] {
+            match DynamicLibrary::open(Some(path)) {
+                Err(Error::InvalidLibraryFile { path: invalid, .. }) => assert_eq!(&invalid, path),
+                Err(error) => panic!("Expected an invalid library file, got: {}", error),
+                Ok(_) => panic!("Opened {} as a library.", path.display()),
+            }
+        }
+        #[cfg(target_os = "linux")]
+        match DynamicLibrary::open(Some(&text)) {
+            Err(error) => assert!(error.to_string().contains("missing ELF magic")),
+            Ok(_) => panic!("Opened a text file as a library."),
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_scoped_symbol() {
+        let first = test_support::build_library(
+            "scoped_first",
+            "int scoped_value(void) { return 1; }",
+            &[],
+        );
+        let second = test_support::build_library(
+            "scoped_second",
+            "#include <unistd.h>\n\
+             int scoped_value(void) { return 2; }\n\
+             int scoped_pid(void) { return getpid(); }\n",
+            &[],
+        );
+        let mut global = DynamicLibrary::options();
+        global.global(true);
+        let (_first, second) = match (global.open(Some(&first)), global.open(Some(&second))) {
+            (Ok(first), Ok(second)) => (first, second),
+            (Err(error), _) | (_, Err(error)) => panic!("Could not load the fixtures: {}", error),
+        };
+        let value = |symbol: *mut u8| unsafe {
+            mem::transmute::<*mut u8, extern "C" fn() -> i32>(symbol)()
+        };
+
+        let Ok(process) = DynamicLibrary::open(None) else {
+            panic!("Could not load self as module");
+        };
+        match unsafe { process.symbol::<u8>("scoped_value") } {
+            Err(error) => panic!("Could not find the global definition: {}", error),
+            Ok(symbol) => assert_eq!(value(symbol), 1),
+        }
+        match unsafe { second.scoped_symbol::<u8>("scoped_value") } {
+            Err(error) => panic!("Could not find the handle's definition: {}", error),
+            Ok(symbol) => assert_eq!(value(symbol), 2),
+        }
+        // Defined by the C library the fixture depends on
+        assert!(unsafe { second.symbol::<u8>("getpid") }.is_ok());
+        match unsafe { second.scoped_symbol::<u8>("getpid") } {
+            Err(Error::OutsideScope(name)) => assert_eq!(name, "getpid"),
+            Err(error) => panic!("Expected a symbol outside of the scope, got: {}", error),
+            Ok(_) => panic!("Found getpid in the fixture itself."),
+        }
+    }
+
+    #[test]
+    fn test_open_with_timeout() {
+        #[cfg(target_os = "linux")]
+        let path = PathBuf::from("libm.so.6");
+        #[cfg(not(target_os = "linux"))]
+        let path = PathBuf::from("libm.dylib");
+        match DynamicLibrary::open_with_timeout(path, Duration::from_secs(30)) {
+            Err(error) => panic!("Could not load the math library in time: {}", error),
+            Ok(libm) => assert!(unsafe { libm.symbol::<u8>("cos") }.is_ok()),
+        }
+
+        // Stands in for a load stuck on the filesystem
+        let timeout = Duration::from_millis(10);
+        let slow = with_timeout(timeout, || thread::sleep(Duration::from_millis(500)));
+        assert_eq!(slow, Err(Error::Timeout(timeout)));
+    }
+
+    #[test]
+    fn test_invalid_symbol_name() {
+        let lib = libm();
+        match unsafe { lib.symbol::<u8>("foo\0bar") } {
+            Err(Error::InvalidSymbolName { name, position }) => {
+                assert_eq!(name, "foo\0bar");
+                assert_eq!(position, 3);
+            }
+            Err(error) => panic!("Expected an invalid symbol name, got: {}", error),
+            Ok(_) => panic!("Resolved a name with an interior NUL."),
+        }
+        match unsafe { lib.symbol_bytes::<u8>(b"foo\0bar\0") } {
+            Err(Error::InvalidSymbolName { position, .. }) => assert_eq!(position, 3),
+            Err(error) => panic!("Expected an invalid symbol name, got: {}", error),
+            Ok(_) => panic!("Resolved a name with an interior NUL."),
+        }
+        assert!(unsafe { lib.symbol_bytes::<u8>(b"cos\0") }.is_ok());
+        assert!(unsafe { lib.symbol_bytes::<u8>(b"cos") }.is_ok());
+    }
+
+    #[test]
+    fn test_reopen_close_failure_policy() {
+        let path =
+            test_support::build_library("reopen_policy", "int reopened(void) { return 1; }", &[]);
+        let failing_close = |lib: &mut DynamicLibrary| {
+            // Gives up the handle as a failed dlclose would
+            lib.closed = true;
+            Err(Error::loader("forced close failure"))
+        };
+        let open = || match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+
+        let mut lib = open();
+        assert_eq!(
+            lib.reopen_closing(CloseFailurePolicy::Abort, failing_close),
+            Err(Error::loader("forced close failure"))
+        );
+        assert_eq!(
+            unsafe { lib.symbol::<u8>("reopened") },
+            Err(Error::HandleClosed)
+        );
+
+        let mut lib = open();
+        assert_eq!(
+            lib.reopen_closing(CloseFailurePolicy::Continue, failing_close),
+            Ok(Some(Error::loader("forced close failure")))
+        );
+        assert!(unsafe { lib.symbol::<u8>("reopened") }.is_ok());
+        assert_eq!(lib.reopen_with(CloseFailurePolicy::Continue), Ok(None));
+    }
+
+    #[test]
+    fn test_install_into() {
+        static COS: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+        let libm = libm();
+        thread::scope(|scope| {
+            let reader = scope.spawn(|| {
+                let cos = loop {
+                    let pointer = COS.load(Ordering::Acquire);
+                    if !pointer.is_null() {
+                        break pointer;
+                    }
+                    thread::yield_now();
+                };
+                unsafe { mem::transmute::<*mut (), extern "C" fn(f64) -> f64>(cos)(0.0) }
+            });
+            if let Err(error) =
+                unsafe { libm.install_into::<extern "C" fn(f64) -> f64>("cos", &COS) }
+            {
+                panic!("Could not install cos: {}", error);
+            }
+            match reader.join() {
+                Err(_) => panic!("The reader thread panicked"),
+                Ok(value) => assert_eq!(value, 1.0),
+            }
+        });
+    }
+
+    #[test]
+    fn test_loading_supported() {
+        assert!(DynamicLibrary::loading_supported());
+    }
+
+    #[test]
+    fn test_reload_if_changed() {
+        let source = |version: i32| format!("int plugin_generation(void) {{ return {version}; }}");
+        let path = test_support::build_library("reload", &source(1), &[]);
+        let generation = |lib: &DynamicLibrary| match unsafe {
+            lib.symbol::<u8>("plugin_generation")
+        } {
+            Err(error) => panic!("Could not find plugin_generation: {}", error),
+            Ok(symbol) => unsafe { mem::transmute::<*mut u8, extern "C" fn() -> i32>(symbol)() },
+        };
+        let mut lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        let mut last_mtime = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+            Err(error) => panic!("Could not read the modification time: {}", error),
+            Ok(mtime) => mtime,
+        };
+        assert_eq!(lib.opened_path(), Some(path.as_path()));
+        assert_eq!(lib.reload_if_changed(&mut last_mtime), Ok(false));
+        assert_eq!(generation(&lib), 1);
+
+        test_support::build_library("reload", &source(2), &[]);
+        let touched = fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(last_mtime + Duration::from_secs(1)));
+        if let Err(error) = touched {
+            panic!("Could not touch the fixture: {}", error);
+        }
+        assert_eq!(lib.reload_if_changed(&mut last_mtime), Ok(true));
+        assert_eq!(generation(&lib), 2);
+        assert_eq!(lib.reload_if_changed(&mut last_mtime), Ok(false));
+    }
 }
@@ -0,0 +1,673 @@
+//! Enumeration of the images currently mapped into the process
+
+#[cfg(target_os = "linux")]
+use std::path::Path;
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{LazyLock, Mutex, PoisonError},
+};
+
+use crate::{DynamicLibrary, Error};
+#[cfg(target_os = "linux")]
+use crate::{dl, options};
+
+/// The images registered with
+/// [`DynamicLibrary::register_for_symbolication`], in registration order
+static SYMBOLICATION: Mutex<Vec<LoadedImage>> = Mutex::new(Vec::new());
+
+/// An object mapped into the process: the executable, a library it was
+/// linked against or one opened at runtime
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedImage {
+    /// The path the loader mapped the image from, as the loader reports it
+    pub path: PathBuf,
+    /// The address the image was loaded at: its load bias on Linux, the
+    /// address of its Mach-O header on macOS
+    pub base: usize,
+}
+
+/// Lists every image mapped into the process, in load order, starting with
+/// the executable
+#[cfg(target_os = "linux")]
+pub fn loaded_libraries() -> Vec<LoadedImage> {
+    use std::{
+        env,
+        ffi::{CStr, OsStr},
+        os::unix::ffi::OsStrExt,
+    };
+
+    unsafe extern "C" fn push_image(
+        info: *mut libc::dl_phdr_info,
+        _size: libc::size_t,
+        images: *mut libc::c_void,
+    ) -> libc::c_int {
+        unsafe {
+            let images = &mut *images.cast::<Vec<LoadedImage>>();
+            let info = &*info;
+            let name = if info.dlpi_name.is_null() {
+                &[][..]
+            } else {
+                CStr::from_ptr(info.dlpi_name).to_bytes()
+            };
+            // The executable is reported with an empty name
+            let path = if name.is_empty() && images.is_empty() {
+                env::current_exe().unwrap_or_default()
+            } else {
+                PathBuf::from(OsStr::from_bytes(name))
+            };
+            images.push(LoadedImage {
+                path,
+                base: info.dlpi_addr as usize,
+            });
+            0
+        }
+    }
+
+    let mut images = Vec::new();
+    unsafe {
+        libc::dl_iterate_phdr(
+            Some(push_image),
+            (&mut images as *mut Vec<LoadedImage>).cast(),
+        );
+    }
+    images
+}
+
+/// Lists every image mapped into the process, in load order, starting with
+/// the executable
+#[cfg(target_os = "macos")]
+pub fn loaded_libraries() -> Vec<LoadedImage> {
+    use std::{
+        ffi::{CStr, OsStr},
+        os::unix::ffi::OsStrExt,
+    };
+
+    // Images loaded concurrently may shift the indices, so missing entries
+    // are skipped rather than trusted
+    (0..unsafe { libc::_dyld_image_count() })
+        .filter_map(|index| unsafe {
+            let name = libc::_dyld_get_image_name(index);
+            let header = libc::_dyld_get_image_header(index);
+            if name.is_null() || header.is_null() {
+                return None;
+            }
+            Some(LoadedImage {
+                path: PathBuf::from(OsStr::from_bytes(CStr::from_ptr(name).to_bytes())),
+                base: header as usize,
+            })
+        })
+        .collect()
+}
+
+/// The addresses resolved by [`DynamicLibrary::global_cached_symbol`],
+/// keyed by the base address of the library and the symbol name
+static SYMBOL_CACHE: LazyLock<Mutex<HashMap<(usize, String), usize>>> =
+    LazyLock::new(Mutex::default);
+
+/// How many lookups [`DynamicLibrary::global_cached_symbol`] made
+#[cfg(test)]
+static SYMBOL_CACHE_MISSES: AtomicUsize = AtomicUsize::new(0);
+
+/// Empties the cache of [`DynamicLibrary::global_cached_symbol`]. Must be
+/// called once a library whose symbols were cached is unloaded, as another
+/// one may be loaded at the same address
+pub fn clear_global_symbol_cache() {
+    SYMBOL_CACHE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+/// Returns the images registered with
+/// [`DynamicLibrary::register_for_symbolication`], for a symbolizer such as
+/// one built on `addr2line` to map backtrace addresses into plugins: an
+/// address belongs to the image with the highest base below it, and its
+/// offset from that base is what to look up in the file at `path`
+pub fn registered_images() -> Vec<LoadedImage> {
+    SYMBOLICATION
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+impl DynamicLibrary {
+    /// Records the path and base address of the library for
+    /// [`registered_images`]. Registering it again updates its entry. The
+    /// entry is kept after the library is closed, when its addresses may be
+    /// reused by another image
+    pub fn register_for_symbolication(&self) -> Result<(), Error> {
+        let base = self.base_address()?;
+        let Some(image) = loaded_libraries()
+            .into_iter()
+            .find(|image| image.base == base)
+        else {
+            return Err(Error::loader(format!(
+                "no loaded image is based at {base:#x}"
+            )));
+        };
+        let mut registered = SYMBOLICATION.lock().unwrap_or_else(PoisonError::into_inner);
+        registered.retain(|entry| entry.base != base);
+        registered.push(image);
+        Ok(())
+    }
+
+    /// Same as [`DynamicLibrary::symbol`], but remembers the address for the
+    /// whole process, keyed by the library's [`base_address`] and `name`,
+    /// so subsystems holding different handles to the same library share
+    /// one lookup. Entries are never invalidated on their own, see
+    /// [`clear_global_symbol_cache`]
+    ///
+    /// [`base_address`]: DynamicLibrary::base_address
+    ///
+    /// # Safety
+    ///
+    /// `T` must match the actual type of the symbol
+    pub unsafe fn global_cached_symbol<T>(&self, name: &str) -> Result<*mut T, Error> {
+        let key = (self.base_address()?, name.to_string());
+        if let Some(&address) = SYMBOL_CACHE
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+        {
+            return Ok(address as *mut T);
+        }
+        #[cfg(test)]
+        SYMBOL_CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+        let address = unsafe { self.symbol::<T>(name)? };
+        SYMBOL_CACHE
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, address as usize);
+        Ok(address)
+    }
+
+    /// Returns the address the library was loaded at: its load bias on
+    /// Linux, which added to a symbol's address in the file gives its
+    /// address in memory, and the address of its Mach-O header on macOS
+    pub fn base_address(&self) -> Result<usize, Error> {
+        #[cfg(target_os = "linux")]
+        {
+            self.mapped_image().map(|image| image.base)
+        }
+        #[cfg(target_os = "macos")]
+        {
+            if self.closed {
+                return Err(Error::HandleClosed);
+            }
+            // dyld hands out the same handle for every open of an image, so
+            // the image is found by opening each loaded one again
+            (0..unsafe { libc::_dyld_image_count() })
+                .find_map(|index| unsafe {
+                    let name = libc::_dyld_get_image_name(index);
+                    if name.is_null() {
+                        return None;
+                    }
+                    let handle = libc::dlopen(name, libc::RTLD_LAZY | libc::RTLD_NOLOAD);
+                    if handle.is_null() {
+                        return None;
+                    }
+                    libc::dlclose(handle);
+                    (handle.cast::<u8>() == self.handle.as_ptr())
+                        .then(|| libc::_dyld_get_image_header(index) as usize)
+                })
+                .ok_or_else(|| Error::loader("failed to find the image of the library"))
+        }
+    }
+}
+
+impl DynamicLibrary {
+    /// Reports whether `addr` lies within the memory the library is mapped
+    /// at, to check that a pointer handed out by a plugin really points
+    /// into it: within one of its loaded segments on Linux, and within the
+    /// image dyld attributes it to on macOS. Heap memory the library
+    /// allocated is not part of it
+    pub fn contains_address(&self, addr: *const ()) -> Result<bool, Error> {
+        let addr = addr as usize;
+        #[cfg(target_os = "linux")]
+        {
+            Ok(self.segments()?.iter().any(|segment| {
+                segment.kind == libc::PT_LOAD
+                    && (segment.address..segment.address + segment.mem_size).contains(&addr)
+            }))
+        }
+        #[cfg(target_os = "macos")]
+        {
+            let base = self.base_address()?;
+            let mut info = unsafe { std::mem::zeroed::<libc::Dl_info>() };
+            let found = unsafe { libc::dladdr(addr as *const libc::c_void, &mut info) } != 0;
+            Ok(found && info.dli_fbase as usize == base)
+        }
+    }
+}
+
+/// How a Mach-O image binds its undefined symbols, see
+/// [`DynamicLibrary::namespace_kind`]
+#[cfg(target_os = "macos")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamespaceKind {
+    /// Each reference records the image expected to define it, and binds to
+    /// that image only (`MH_TWOLEVEL`), the default for modern images
+    TwoLevel,
+    /// References bind to the first definition found in any loaded image,
+    /// as on ELF platforms, so another image can interpose on them
+    Flat,
+}
+
+#[cfg(target_os = "macos")]
+impl DynamicLibrary {
+    /// Reports whether the loaded image of the library was linked with a
+    /// two-level or a flat namespace, read from the flags of its Mach-O
+    /// header. macOS only.
+    ///
+    /// This decides how the library's own references bind, and so whether
+    /// another library defining the same name can take them over; in a
+    /// two-level namespace it cannot, unless the process runs with
+    /// `DYLD_FORCE_FLAT_NAMESPACE`. [`DynamicLibrary::symbol`] looks the name
+    /// up in the library and then in the images it depends on either way
+    pub fn namespace_kind(&self) -> Result<NamespaceKind, Error> {
+        const MH_TWOLEVEL: u32 = 0x80;
+        // `flags` follows six 32-bit fields in both `mach_header` and
+        // `mach_header_64`
+        const FLAGS_OFFSET: usize = 24;
+        let header = self.base_address()?;
+        let flags = unsafe { ((header + FLAGS_OFFSET) as *const u32).read_unaligned() };
+        Ok(if flags & MH_TWOLEVEL != 0 {
+            NamespaceKind::TwoLevel
+        } else {
+            NamespaceKind::Flat
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl DynamicLibrary {
+    /// Lazily opens a dynamic library and calls `on_dependency` with the
+    /// path of every image the load mapped: the library itself and the
+    /// dependencies that were not loaded yet. Found by comparing
+    /// [`loaded_libraries`] before and after the load, so images mapped by
+    /// other threads meanwhile are reported too
+    pub fn open_observed(
+        filename: &Path,
+        mut on_dependency: impl FnMut(&Path),
+    ) -> Result<DynamicLibrary, Error> {
+        let before = loaded_libraries();
+        let lib = DynamicLibrary::open(Some(filename))?;
+        loaded_libraries()
+            .iter()
+            .filter(|image| !before.contains(image))
+            .for_each(|image| on_dependency(&image.path));
+        Ok(lib)
+    }
+
+    /// Lazily opens a dynamic library, reporting whether this loaded it:
+    /// `false` when it was already loaded, in which case the handle only
+    /// takes another reference to it. Probes with `RTLD_NOLOAD` first, then
+    /// opens the library if the probe found nothing. Another thread loading
+    /// the same library between the two makes this report `true` although
+    /// that thread loaded it, so the result is a hint, not a guarantee of
+    /// ownership
+    pub fn open_or_existing(filename: &Path) -> Result<(DynamicLibrary, bool), Error> {
+        let rewritten = options::rewrite_path(filename);
+        let path = rewritten.as_deref().unwrap_or(filename);
+        options::check_strict_path(Some(path))?;
+        let flags = options::default_binding();
+        let existing = dl::open(Some(path.as_os_str()), flags | libc::RTLD_NOLOAD)?;
+        let (handle, fresh) = if existing.is_null() {
+            (dl::open(Some(path.as_os_str()), flags)?, true)
+        } else {
+            (existing, false)
+        };
+        let lib = DynamicLibrary::from_handle(handle, flags, Some(path.to_path_buf()))?;
+        Ok((lib, fresh))
+    }
+
+    /// Same as [`DynamicLibrary::open_observed`] but returns the paths of
+    /// the images the load mapped, for accounting the address space each
+    /// plugin brings in
+    pub fn open_accounted(filename: &Path) -> Result<(DynamicLibrary, Vec<PathBuf>), Error> {
+        let mut mapped = Vec::new();
+        let lib = DynamicLibrary::open_observed(filename, |path| mapped.push(path.to_path_buf()))?;
+        Ok((lib, mapped))
+    }
+}
+
+/// Reports which of `names` are exported by more than one loaded image,
+/// with the paths of the images defining each, in load order. When such
+/// images share a scope, which definition a lookup binds to depends on the
+/// load order. Exports are read from the images' files on disk; images
+/// without a file, such as the vDSO, and those whose file cannot be read or
+/// parsed, such as one replaced since it was loaded, are skipped
+#[cfg(target_os = "linux")]
+pub fn find_symbol_conflicts(names: &[&str]) -> Vec<(String, Vec<PathBuf>)> {
+    let mut definers = vec![Vec::new(); names.len()];
+    for image in loaded_libraries() {
+        if !image.path.is_file() {
+            continue;
+        }
+        let Ok(exports) = crate::exported_symbols_file(&image.path) else {
+            continue;
+        };
+        for (name, definers) in names.iter().zip(&mut definers) {
+            if exports.contains(*name) {
+                definers.push(image.path.clone());
+            }
+        }
+    }
+    names
+        .iter()
+        .zip(definers)
+        .filter(|(_, definers)| definers.len() > 1)
+        .map(|(name, definers)| (name.to_string(), definers))
+        .collect()
+}
+
+/// Lists the loaded images whose path contains `pattern`, or matches it as a
+/// glob when it contains `*` or `?`. Answers questions such as "is any
+/// version of libssl loaded?" with `find_loaded("libssl")`
+pub fn find_loaded(pattern: &str) -> Vec<LoadedImage> {
+    let is_glob = pattern.contains(['*', '?']);
+    loaded_libraries()
+        .into_iter()
+        .filter(|image| {
+            let path = image.path.to_string_lossy();
+            if is_glob {
+                glob_match(pattern.as_bytes(), path.as_bytes())
+            } else {
+                path.contains(pattern)
+            }
+        })
+        .collect()
+}
+
+/// Matches `text` against `pattern`, where `*` stands for any run of bytes
+/// and `?` for any single byte
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Where to resume after the last `*` if the bytes after it mismatch
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&byte) if byte == b'?' || byte == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    t = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|byte| *byte == b'*')
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(target_os = "linux")]
+    use std::fs;
+
+    use super::*;
+    use crate::{DynamicLibrary, test_support};
+
+    #[test]
+    fn test_find_loaded() {
+        let path = test_support::build_library("find_loaded", "int found(void) { return 1; }", &[]);
+        assert!(find_loaded("libfind_loaded").is_empty());
+
+        let _lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        let found = find_loaded("libfind_loaded");
+        assert_eq!(found.len(), 1);
+        assert!(
+            found[0]
+                .path
+                .ends_with(path.file_name().unwrap_or_default())
+        );
+        assert_eq!(find_loaded("*/libfind_load?d.*"), found);
+        assert!(find_loaded("libfind_loaded*").is_empty());
+    }
+
+    #[test]
+    fn test_global_cached_symbol() {
+        let path = test_support::build_library(
+            "global_cached",
+            "int cached_value(void) { return 9; }",
+            &[],
+        );
+        let open = || match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        let (first, second) = (open(), open());
+        let misses = SYMBOL_CACHE_MISSES.load(Ordering::Relaxed);
+        let from_first = unsafe { first.global_cached_symbol::<u8>("cached_value") };
+        let from_second = unsafe { second.global_cached_symbol::<u8>("cached_value") };
+        assert!(from_first.is_ok());
+        assert_eq!(from_first, from_second);
+        assert_eq!(SYMBOL_CACHE_MISSES.load(Ordering::Relaxed), misses + 1);
+        assert_eq!(from_first, unsafe { first.symbol::<u8>("cached_value") });
+        assert!(unsafe { second.global_cached_symbol::<u8>("not_cached") }.is_err());
+    }
+
+    #[test]
+    fn test_register_for_symbolication() {
+        let path = test_support::build_library(
+            "symbolication",
+            "int symbolicated(int x) { return x + 1; }",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(lib.register_for_symbolication(), Ok(()));
+        assert_eq!(lib.register_for_symbolication(), Ok(()));
+
+        let address = match unsafe { lib.symbol::<()>("symbolicated") } {
+            Err(error) => panic!("Could not find symbolicated: {}", error),
+            Ok(address) => address,
+        };
+        let Some(info) = crate::address::symbol_info(address) else {
+            panic!("No image contains symbolicated");
+        };
+        assert_eq!(info.symbol.as_deref(), Some("symbolicated"));
+        assert_eq!(info.symbol_address, Some(address as usize));
+        let registered: Vec<_> = registered_images()
+            .into_iter()
+            .filter(|image| image.path == info.image)
+            .collect();
+        assert_eq!(registered.len(), 1);
+        assert_eq!(registered[0].base, info.image_base);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_contains_address() {
+        let libm = match DynamicLibrary::open(Some(Path::new("libm.so.6"))) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(libm) => libm,
+        };
+        let cos = match unsafe { libm.symbol::<()>("cos") } {
+            Err(error) => panic!("Could not find cos: {}", error),
+            Ok(cos) => cos,
+        };
+        assert_eq!(libm.contains_address(cos), Ok(true));
+        let local = 0_u8;
+        assert_eq!(libm.contains_address((&raw const local).cast()), Ok(false));
+        assert_eq!(libm.contains_address(std::ptr::null()), Ok(false));
+        let elsewhere = test_contains_address as fn() as *const ();
+        assert_eq!(libm.contains_address(elsewhere), Ok(false));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_open_or_existing() {
+        let path = test_support::build_library(
+            "open_or_existing",
+            "int existing(void) { return 1; }",
+            &[],
+        );
+        let (first, fresh) = match DynamicLibrary::open_or_existing(&path) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(opened) => opened,
+        };
+        assert!(fresh);
+        let (second, fresh) = match DynamicLibrary::open_or_existing(&path) {
+            Err(error) => panic!("Could not open the fixture again: {}", error),
+            Ok(opened) => opened,
+        };
+        assert!(!fresh);
+        assert!(unsafe { second.symbol::<u8>("existing") }.is_ok());
+        drop((first, second));
+        assert!(DynamicLibrary::open_or_existing(Path::new("/does/not/exist.so")).is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn test_namespace_kind() {
+        let lib =
+            match DynamicLibrary::open(Some(std::path::Path::new("/usr/lib/libSystem.B.dylib"))) {
+                Err(error) => panic!("Could not load libSystem: {}", error),
+                Ok(lib) => lib,
+            };
+        assert_eq!(lib.namespace_kind(), Ok(NamespaceKind::TwoLevel));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_open_observed() {
+        let dir = test_support::fixture_dir().join("open_observed");
+        let dependency = test_support::build_library_in(
+            &dir,
+            "observed_dependency",
+            "int dependency(void) { return 1; }",
+            &[],
+        );
+        let library = test_support::build_library_in(
+            &dir,
+            "observed",
+            "int dependency(void);\nint observed(void) { return dependency(); }",
+            &[
+                &format!("-L{}", dir.display()),
+                "-Wl,-rpath,$ORIGIN",
+                "-lobserved_dependency",
+            ],
+        );
+
+        let mut mapped = Vec::new();
+        let opened =
+            DynamicLibrary::open_observed(&library, |path| mapped.push(path.to_path_buf()));
+        if let Err(error) = opened {
+            panic!("Could not load the fixture: {}", error);
+        }
+        assert!(
+            mapped
+                .iter()
+                .any(|path| path.ends_with(library.file_name().unwrap_or_default()))
+        );
+        assert!(
+            mapped
+                .iter()
+                .any(|path| path.ends_with(dependency.file_name().unwrap_or_default()))
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_open_accounted() {
+        let dir = test_support::fixture_dir().join("open_accounted");
+        test_support::build_library_in(
+            &dir,
+            "accounted_dependency",
+            "int dependency(void) { return 1; }",
+            &[],
+        );
+        let library = test_support::build_library_in(
+            &dir,
+            "accounted",
+            "int dependency(void);\nint accounted(void) { return dependency(); }",
+            &[
+                &format!("-L{}", dir.display()),
+                "-Wl,-rpath,$ORIGIN",
+                "-laccounted_dependency",
+            ],
+        );
+
+        let mapped = match DynamicLibrary::open_accounted(&library) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok((_lib, mapped)) => mapped,
+        };
+        assert!(mapped.contains(&library));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_find_symbol_conflicts() {
+        let source = "int conflicting_symbol(void) { return 1; }";
+        let first = test_support::build_library("conflict_first", source, &[]);
+        let second = test_support::build_library("conflict_second", source, &[]);
+        let replaced = test_support::build_library("conflict_replaced", source, &[]);
+        let libs = [&first, &second, &replaced].map(|path| {
+            match DynamicLibrary::options().global(true).open(Some(path)) {
+                Err(error) => panic!("Could not load the fixture: {}", error),
+                Ok(lib) => lib,
+            }
+        });
+        // Replaced on disk by a file that is not a library, leaving the
+        // loaded image as it was
+        let written = fs::remove_file(&replaced).and_then(|()| fs::write(&replaced, "not ELF"));
+        if let Err(error) = written {
+            panic!("Could not replace {}: {}", replaced.display(), error);
+        }
+
+        assert_eq!(
+            find_symbol_conflicts(&["conflicting_symbol", "conflict_free_symbol"]),
+            [("conflicting_symbol".to_string(), vec![first, second])]
+        );
+        drop(libs);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_base_address() {
+        let path =
+            test_support::build_library("base_address", "int based(void) { return 1; }", &[]);
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        let in_file = crate::elf::ElfFile::read(&path)
+            .and_then(|elf| elf.dynamic_symbols())
+            .map(|symbols| symbols.into_iter().find(|symbol| symbol.name == "based"));
+        let offset = match in_file {
+            Err(error) => panic!("Could not read the fixture's symbols: {}", error),
+            Ok(None) => panic!("The fixture does not export `based`"),
+            Ok(Some(symbol)) => symbol.value as usize,
+        };
+        match (lib.base_address(), unsafe { lib.symbol::<u8>("based") }) {
+            (Ok(base), Ok(symbol)) => assert_eq!(base + offset, symbol as usize),
+            (Err(error), _) | (_, Err(error)) => panic!("Could not locate the symbol: {}", error),
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*ssl*", b"/usr/lib/libssl.so.3"));
+        assert!(glob_match(b"/usr/lib/lib?sl.so*", b"/usr/lib/libssl.so.3"));
+        assert!(glob_match(b"*", b""));
+        assert!(!glob_match(b"*.dylib", b"/usr/lib/libssl.so.3"));
+        assert!(!glob_match(b"lib?", b"lib"));
+    }
+}
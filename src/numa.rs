@@ -0,0 +1,104 @@
+//! NUMA placement hints for the memory a library is loaded into
+
+use std::{path::Path, ptr};
+
+use crate::{DynamicLibrary, Error};
+
+const MPOL_DEFAULT: libc::c_int = 0;
+const MPOL_PREFERRED: libc::c_int = 1;
+
+/// Bits in the node masks exchanged with the kernel, enough for the node
+/// count of any current machine
+const MAX_NODES: usize = 1024;
+const MASK_WORDS: usize = MAX_NODES / u64::BITS as usize;
+
+impl DynamicLibrary {
+    /// Lazily opens a dynamic library while the calling thread prefers to
+    /// allocate memory on NUMA node `node`, restoring its previous memory
+    /// policy afterwards.
+    ///
+    /// The hint is advisory and best-effort: it only affects the pages the
+    /// loader touches during the load, pages already in the page cache stay
+    /// where they are, and it is silently skipped when the kernel has no
+    /// NUMA support or no such node. Relies on the glibc loader mapping the
+    /// library from the calling thread
+    pub fn open_on_node(filename: &Path, node: u32) -> Result<DynamicLibrary, Error> {
+        let previous = MemoryPolicy::current();
+        let mut preferred = [0u64; MASK_WORDS];
+        if let Some(word) = preferred.get_mut(node as usize / 64) {
+            *word = 1 << (node % 64);
+            MemoryPolicy {
+                mode: MPOL_PREFERRED,
+                nodes: preferred,
+            }
+            .apply();
+        }
+        let lib = DynamicLibrary::open(Some(filename));
+        previous.apply();
+        lib
+    }
+}
+
+/// The memory policy of the calling thread
+struct MemoryPolicy {
+    mode: libc::c_int,
+    nodes: [u64; MASK_WORDS],
+}
+
+impl MemoryPolicy {
+    /// Returns the thread's policy, the default one if it cannot be read
+    fn current() -> MemoryPolicy {
+        let mut policy = MemoryPolicy {
+            mode: MPOL_DEFAULT,
+            nodes: [0; MASK_WORDS],
+        };
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_get_mempolicy,
+                &mut policy.mode as *mut libc::c_int,
+                policy.nodes.as_mut_ptr(),
+                MAX_NODES,
+                0usize,
+                0usize,
+            )
+        };
+        if result != 0 {
+            policy.mode = MPOL_DEFAULT;
+            policy.nodes = [0; MASK_WORDS];
+        }
+        policy
+    }
+
+    /// Makes this the thread's policy, ignoring failures
+    fn apply(&self) {
+        let nodes = if self.mode == MPOL_DEFAULT {
+            ptr::null()
+        } else {
+            self.nodes.as_ptr()
+        };
+        unsafe {
+            libc::syscall(libc::SYS_set_mempolicy, self.mode, nodes, MAX_NODES);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn test_open_on_node() {
+        if !Path::new("/sys/devices/system/node/node0").exists() {
+            return;
+        }
+        let path = test_support::build_library("numa", "int on_node(void) { return 1; }", &[]);
+        let before = MemoryPolicy::current();
+        match DynamicLibrary::open_on_node(&path, 0) {
+            Err(error) => panic!("Could not load the fixture on node 0: {}", error),
+            Ok(lib) => assert!(unsafe { lib.symbol::<u8>("on_node") }.is_ok()),
+        }
+        let after = MemoryPolicy::current();
+        assert_eq!((before.mode, before.nodes), (after.mode, after.nodes));
+    }
+}
@@ -0,0 +1,534 @@
+use std::{
+    cell::Cell,
+    env, fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, PoisonError, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use crate::{DynamicLibrary, Error, dl};
+
+/// Options and flags which can be used to configure how a library is opened,
+/// in the style of [`std::fs::OpenOptions`]
+///
+/// ```no_run
+/// use std::{cell::Cell, env, path::Path};
+///
+/// use dynamite::DynamicLibrary;
+///
+/// let lib = DynamicLibrary::options()
+///     .global(true)
+///     .open(Some(Path::new("libplugin.so")));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    now: Option<bool>,
+    global: bool,
+    group: bool,
+    world: bool,
+    parent: bool,
+    nodelete: bool,
+}
+
+impl OpenOptions {
+    /// Debugging aid: when this environment variable is `1`, libraries whose
+    /// binding was not chosen with [`OpenOptions::now`] are bound eagerly,
+    /// including by [`DynamicLibrary::open`]. Surfaces missing symbols at
+    /// load time without recompiling
+    pub const FORCE_NOW_VAR: &'static str = "DYLIB_FORCE_NOW";
+
+    /// Creates options that open libraries lazily bound and with local scope,
+    /// the same as [`DynamicLibrary::open`]
+    pub const fn new() -> OpenOptions {
+        OpenOptions {
+            now: None,
+            global: false,
+            group: false,
+            world: false,
+            parent: false,
+            nodelete: false,
+        }
+    }
+
+    /// Resolves every undefined symbol of the library when it is opened
+    /// (`RTLD_NOW`) rather than on first use (`RTLD_LAZY`). Takes precedence
+    /// over [`set_default_binding`] and [`OpenOptions::FORCE_NOW_VAR`]
+    pub const fn now(&mut self, now: bool) -> &mut OpenOptions {
+        self.now = Some(now);
+        self
+    }
+
+    /// Makes the symbols of the library available for resolving the symbols
+    /// of libraries loaded later (`RTLD_GLOBAL`)
+    pub const fn global(&mut self, global: bool) -> &mut OpenOptions {
+        self.global = global;
+        self
+    }
+
+    /// Resolves the symbols of the library only within its own group, the
+    /// library and its dependencies (`RTLD_GROUP`). Solaris and illumos only,
+    /// a no-op elsewhere
+    pub const fn group(&mut self, group: bool) -> &mut OpenOptions {
+        self.group = group;
+        self
+    }
+
+    /// Resolves the symbols of the library within the global scope
+    /// (`RTLD_WORLD`). Solaris and illumos only, a no-op elsewhere
+    pub const fn world(&mut self, world: bool) -> &mut OpenOptions {
+        self.world = world;
+        self
+    }
+
+    /// Makes the symbols of the object that opens the library available to
+    /// it (`RTLD_PARENT`). Solaris and illumos only, a no-op elsewhere
+    pub const fn parent(&mut self, parent: bool) -> &mut OpenOptions {
+        self.parent = parent;
+        self
+    }
+
+    /// Keeps the library loaded for the rest of the process, even once every
+    /// handle to it is closed (`RTLD_NODELETE`), so pointers into it, such
+    /// as callbacks registered with other libraries, never dangle
+    pub const fn nodelete(&mut self, nodelete: bool) -> &mut OpenOptions {
+        self.nodelete = nodelete;
+        self
+    }
+
+    /// Opens a dynamic library with these options. When passed None it gives
+    /// a handle to the calling process
+    pub fn open(&self, filename: Option<&Path>) -> Result<DynamicLibrary, Error> {
+        let rewritten = filename.and_then(rewrite_path);
+        let filename = rewritten.as_deref().or(filename);
+        check_strict_path(filename)?;
+        let flags = self.flags();
+        dl::open(filename.map(|path| path.as_os_str()), flags)
+            .and_then(|handle| DynamicLibrary::from_opened(handle, flags, filename))
+    }
+
+    /// Reconstructs the options that produce the native `dlopen` flags
+    /// `flags`. Approximate: bits with no matching option are dropped, and
+    /// flags with neither `RTLD_LAZY` nor `RTLD_NOW` leave the binding to the
+    /// defaults
+    pub const fn from_native(flags: libc::c_int) -> OpenOptions {
+        const fn has(flags: libc::c_int, bit: libc::c_int) -> bool {
+            bit != 0 && flags & bit == bit
+        }
+        OpenOptions {
+            now: if has(flags, dl::NOW) {
+                Some(true)
+            } else if has(flags, dl::LAZY) {
+                Some(false)
+            } else {
+                None
+            },
+            global: has(flags, dl::GLOBAL),
+            group: has(flags, dl::GROUP),
+            world: has(flags, dl::WORLD),
+            parent: has(flags, dl::PARENT),
+            nodelete: has(flags, dl::NODELETE),
+        }
+    }
+
+    fn flags(&self) -> libc::c_int {
+        let mut flags = match self.now {
+            Some(true) => dl::NOW,
+            Some(false) => dl::LAZY,
+            None => default_binding(),
+        };
+        if self.global {
+            flags |= dl::GLOBAL;
+        }
+        if self.group {
+            flags |= dl::GROUP;
+        }
+        if self.world {
+            flags |= dl::WORLD;
+        }
+        if self.parent {
+            flags |= dl::PARENT;
+        }
+        if self.nodelete {
+            flags |= dl::NODELETE;
+        }
+        flags
+    }
+}
+
+/// Returns the native `dlopen` flags `options` open libraries with. When no
+/// binding was chosen, the current default applies, see
+/// [`set_default_binding`]
+pub fn native_open_flags(options: &OpenOptions) -> libc::c_int {
+    options.flags()
+}
+
+impl DynamicLibrary {
+    /// Opens the library described by `spec`, a path or file name
+    /// optionally followed by a colon and a comma-separated list of flags,
+    /// such as `libfoo.so:now,global`, to configure plugins through a
+    /// single string. The flags are `lazy`, `now`, `local`, `global`,
+    /// `group`, `world`, `parent` and `nodelete`, each setting the [`OpenOptions`]
+    /// option of the same name, later ones overriding earlier ones. Fails
+    /// with [`Error::InvalidOpenSpec`] on any other flag. Everything after
+    /// the last colon is taken as flags, so a path containing a colon must
+    /// be followed by one
+    pub fn open_spec(spec: &str) -> Result<DynamicLibrary, Error> {
+        let (path, flags) = spec.rsplit_once(':').unwrap_or((spec, ""));
+        let mut options = OpenOptions::new();
+        for token in flags
+            .split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+        {
+            match token {
+                "lazy" => options.now(false),
+                "now" => options.now(true),
+                "local" => options.global(false),
+                "global" => options.global(true),
+                "group" => options.group(true),
+                "world" => options.world(true),
+                "parent" => options.parent(true),
+                "nodelete" => options.nodelete(true),
+                _ => {
+                    return Err(Error::InvalidOpenSpec {
+                        spec: spec.to_string(),
+                        token: token.to_string(),
+                    });
+                }
+            };
+        }
+        options.open(Some(Path::new(path)))
+    }
+}
+
+/// When the undefined symbols of a library are resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// On first use (`RTLD_LAZY`)
+    Lazy,
+    /// When the library is opened (`RTLD_NOW`)
+    Now,
+}
+
+thread_local! {
+    static DEFAULT_BINDING: Cell<Option<Binding>> = const { Cell::new(None) };
+}
+
+/// Sets the binding of the libraries opened by the current thread without
+/// choosing one through [`OpenOptions::now`], including by
+/// [`DynamicLibrary::open`]. Takes precedence over
+/// [`OpenOptions::FORCE_NOW_VAR`]
+pub fn set_default_binding(binding: Binding) {
+    DEFAULT_BINDING.set(Some(binding));
+}
+
+/// The binding used when none was chosen explicitly: the thread's default,
+/// otherwise lazy unless forced through [`OpenOptions::FORCE_NOW_VAR`]
+pub(crate) fn default_binding() -> libc::c_int {
+    match DEFAULT_BINDING.get() {
+        Some(Binding::Lazy) => dl::LAZY,
+        Some(Binding::Now) => dl::NOW,
+        None => match env::var_os(OpenOptions::FORCE_NOW_VAR) {
+            Some(value) if value == "1" => dl::NOW,
+            _ => dl::LAZY,
+        },
+    }
+}
+
+static STRICT_PATHS: AtomicBool = AtomicBool::new(false);
+
+/// Makes [`DynamicLibrary::open`], [`DynamicLibrary::open_cstr`] and
+/// [`OpenOptions::open`] fail with [`Error::RelativePathRejected`] when
+/// given a relative path or a bare file name, for the whole process, so no
+/// library is found through the loader's search. Handles to the process
+/// itself are still allowed. [`DynamicLibrary::open_absolute`] enforces
+/// this whether or not it is enabled
+pub fn set_strict_paths(enabled: bool) {
+    STRICT_PATHS.store(enabled, Ordering::Relaxed);
+}
+
+type PathRewriter = dyn Fn(&Path) -> PathBuf + Send + Sync;
+
+static PATH_REWRITER: RwLock<Option<Arc<PathRewriter>>> = RwLock::new(None);
+
+/// Makes every open of a library by path, for the whole process, open the
+/// path `rewriter` returns for it instead, to remap the configured plugin
+/// paths of a relocated or containerized deployment in one place, e.g.
+/// `/opt/app` to `/mnt/app`. Replaces any earlier rewriter; paths are left
+/// as they are until one is set or after [`clear_path_rewriter`]. The
+/// rewritten path is the one checked by [`set_strict_paths`] and
+/// [`DynamicLibrary::open_secure`] and reported by
+/// [`DynamicLibrary::opened_path`]
+pub fn set_path_rewriter(rewriter: impl Fn(&Path) -> PathBuf + Send + Sync + 'static) {
+    *PATH_REWRITER
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(rewriter));
+}
+
+/// Removes the rewriter set with [`set_path_rewriter`], so paths are opened
+/// as they are again
+pub fn clear_path_rewriter() {
+    *PATH_REWRITER
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Applies the rewriter set with [`set_path_rewriter`], `None` if there is
+/// none. The rewriter runs without the lock held, so it may open libraries
+pub(crate) fn rewrite_path(path: &Path) -> Option<PathBuf> {
+    let rewriter = PATH_REWRITER
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()?;
+    Some(rewriter(path))
+}
+
+/// Where and with which permissions the loaders working on a private copy
+/// of a library, [`DynamicLibrary::open_shadow_copy`],
+/// [`DynamicLibrary::open_embedded`] and [`DynamicLibrary::open_bundle`],
+/// create it, see [`set_temp_config`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TempConfig {
+    /// The directory the private directory holding the copies is created
+    /// in, the system's temporary directory when `None`
+    pub dir: Option<PathBuf>,
+    /// The permission bits of the copies, including the in-memory file
+    /// [`DynamicLibrary::open_embedded`] creates on Linux
+    pub mode: u32,
+}
+
+impl Default for TempConfig {
+    /// The system's temporary directory, and files only the current user
+    /// can read and write (`0600`)
+    fn default() -> TempConfig {
+        TempConfig {
+            dir: None,
+            mode: 0o600,
+        }
+    }
+}
+
+static TEMP_CONFIG: RwLock<Option<TempConfig>> = RwLock::new(None);
+
+/// Sets where and with which permissions temporary copies of libraries are
+/// created, for the whole process. The copies are always created within a
+/// new directory only the current user can access, and removed once loaded
+pub fn set_temp_config(config: TempConfig) {
+    *TEMP_CONFIG.write().unwrap_or_else(PoisonError::into_inner) = Some(config);
+}
+
+/// The configuration set with [`set_temp_config`], or the default one
+pub(crate) fn temp_config() -> TempConfig {
+    TEMP_CONFIG
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Rejects a relative `filename` when [`set_strict_paths`] is enabled
+pub(crate) fn check_strict_path(filename: Option<&Path>) -> Result<(), Error> {
+    match filename {
+        Some(path) if STRICT_PATHS.load(Ordering::Relaxed) => check_absolute(path),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn check_absolute(path: &Path) -> Result<(), Error> {
+    if path.is_absolute() {
+        Ok(())
+    } else {
+        Err(Error::RelativePathRejected(path.to_path_buf()))
+    }
+}
+
+/// Fails with [`Error::InsecurePermissions`] unless `path` is owned by
+/// root or the effective user and is not writable by its group or others
+pub(crate) fn check_secure(path: &Path) -> Result<(), Error> {
+    let metadata = fs::metadata(path).map_err(|err| Error::io(path, &err))?;
+    let insecure = |reason: String| {
+        Err(Error::InsecurePermissions {
+            path: path.to_path_buf(),
+            reason,
+        })
+    };
+    let owner = metadata.uid();
+    if owner != 0 && owner != unsafe { libc::geteuid() } {
+        return insecure(format!("owned by user {owner}"));
+    }
+    let mode = metadata.mode() & 0o777;
+    if mode & 0o022 != 0 {
+        return insecure(format!("writable by group or others (mode {mode:o})"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{self, ENV_LOCK};
+
+    #[test]
+    fn test_symbol_scope_flags() {
+        let mut options = OpenOptions::new();
+        options.group(true).world(true).parent(true);
+
+        #[cfg(any(target_os = "solaris", target_os = "illumos"))]
+        assert_eq!(
+            options.flags(),
+            dl::LAZY | libc::RTLD_GROUP | libc::RTLD_WORLD | libc::RTLD_PARENT
+        );
+        #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
+        assert_eq!(options.flags(), OpenOptions::new().flags());
+
+        if let Err(error) = options.open(None) {
+            panic!("Could not load self as module: {}", error);
+        }
+    }
+
+    #[test]
+    fn test_force_now_env() {
+        let path = test_support::build_library(
+            "force_now",
+            "void missing_function(void);\n\
+             void call_missing(void) { missing_function(); }\n",
+            &[],
+        );
+        let _guard = ENV_LOCK.lock();
+        let previous = env::var_os(OpenOptions::FORCE_NOW_VAR);
+
+        unsafe { env::set_var(OpenOptions::FORCE_NOW_VAR, "1") };
+        let forced = DynamicLibrary::open(Some(&path));
+        let explicit = OpenOptions::new().now(false).open(Some(&path));
+        unsafe {
+            match previous {
+                Some(previous) => env::set_var(OpenOptions::FORCE_NOW_VAR, previous),
+                None => env::remove_var(OpenOptions::FORCE_NOW_VAR),
+            }
+        };
+
+        match forced {
+            Err(error) => assert!(error.to_string().contains("missing_function")),
+            Ok(_) => panic!("Opened a library with a missing symbol eagerly."),
+        }
+        if let Err(error) = explicit {
+            panic!(
+                "Explicit lazy binding did not override the environment: {}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_thread_default_binding() {
+        let path = test_support::build_library(
+            "thread_binding",
+            "void missing_function(void);\n\
+             void call_missing(void) { missing_function(); }\n",
+            &[],
+        );
+        // Each test runs on its own thread, so the default does not leak
+        set_default_binding(Binding::Now);
+        match DynamicLibrary::open(Some(&path)) {
+            Err(error) => assert!(error.to_string().contains("missing_function")),
+            Ok(_) => panic!("Opened a library with a missing symbol eagerly."),
+        }
+        if let Err(error) = OpenOptions::new().now(false).open(Some(&path)) {
+            panic!(
+                "Explicit lazy binding did not override the thread default: {}",
+                error
+            );
+        }
+        set_default_binding(Binding::Lazy);
+        if let Err(error) = DynamicLibrary::open(Some(&path)) {
+            panic!("Could not open lazily: {}", error);
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_open_spec() {
+        match DynamicLibrary::open_spec("libm.so.6:now") {
+            Err(error) => panic!("Could not open the math library: {}", error),
+            Ok(lib) => assert_eq!(lib.open_flags(), *OpenOptions::new().now(true)),
+        }
+        match DynamicLibrary::open_spec("libm.so.6: lazy, global") {
+            Err(error) => panic!("Could not open the math library: {}", error),
+            Ok(lib) => assert_eq!(
+                lib.open_flags(),
+                *OpenOptions::new().now(false).global(true)
+            ),
+        }
+
+        let path = test_support::build_library(
+            "open_spec",
+            "void missing_function(void);\n\
+             void call_missing(void) { missing_function(); }\n",
+            &[],
+        );
+        let spec = |flags: &str| format!("{}:{flags}", path.display());
+        assert!(DynamicLibrary::open_spec(&spec("lazy")).is_ok());
+        match DynamicLibrary::open_spec(&spec("lazy,now")) {
+            Err(error) => assert!(error.to_string().contains("missing_function")),
+            Ok(_) => panic!("Opened a library with a missing symbol eagerly."),
+        }
+
+        match DynamicLibrary::open_spec("x:bogusflag") {
+            Err(error) => {
+                assert_eq!(
+                    error,
+                    Error::InvalidOpenSpec {
+                        spec: "x:bogusflag".to_string(),
+                        token: "bogusflag".to_string()
+                    }
+                );
+                assert!(error.to_string().contains("`bogusflag`"));
+            }
+            Ok(_) => panic!("Opened a library with an unknown flag"),
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_native_open_flags_round_trip() {
+        let mut options = OpenOptions::new();
+        options.now(false).global(true);
+        let flags = native_open_flags(&options);
+        assert_eq!(flags, libc::RTLD_LAZY | libc::RTLD_GLOBAL);
+        assert_eq!(flags, 0x101);
+        assert_eq!(OpenOptions::from_native(flags), options);
+
+        let mut eager = OpenOptions::new();
+        eager.now(true);
+        assert_eq!(OpenOptions::from_native(libc::RTLD_NOW), eager);
+
+        let mut pinned = OpenOptions::new();
+        pinned.now(true).nodelete(true);
+        assert_eq!(
+            native_open_flags(&pinned),
+            libc::RTLD_NOW | libc::RTLD_NODELETE
+        );
+        assert_eq!(OpenOptions::from_native(native_open_flags(&pinned)), pinned);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_nodelete() {
+        let path = test_support::build_library("nodelete", "int pinned(void) { return 5; }", &[]);
+        match DynamicLibrary::open_spec(&format!("{}:now,nodelete", path.display())) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => assert_eq!(
+                lib.open_flags(),
+                *OpenOptions::new().now(true).nodelete(true)
+            ),
+        }
+        assert!(
+            crate::loaded_libraries()
+                .iter()
+                .any(|image| image.path == path)
+        );
+    }
+}
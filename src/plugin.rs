@@ -0,0 +1,354 @@
+//! A registry owning the libraries a framework loads as plugins
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, mem,
+    path::{Path, PathBuf},
+};
+
+use crate::{DynamicLibrary, Error};
+
+/// The symbol a plugin exports its descriptor under, see
+/// [`DynamicLibrary::load_plugin_descriptor`]
+pub const PLUGIN_DESCRIPTOR_SYMBOL: &str = "DYLIB_PLUGIN_DESCRIPTOR";
+
+/// The value the first field of a plugin descriptor must hold, the bytes
+/// `DYNPLUG1` read as a little endian `u64`: `0x3147554c504e5944`
+pub const PLUGIN_DESCRIPTOR_MAGIC: u64 = u64::from_le_bytes(*b"DYNPLUG1");
+
+impl DynamicLibrary {
+    /// Reads the descriptor the library exports as
+    /// [`PLUGIN_DESCRIPTOR_SYMBOL`], the entry point of plugins following
+    /// this convention: a `#[repr(C)]` struct whose first field is a `u64`
+    /// holding [`PLUGIN_DESCRIPTOR_MAGIC`], typically followed by the
+    /// plugin's name, version and a table of functions. Fails with
+    /// [`Error::DescriptorMagicMismatch`] if the magic is missing, and with
+    /// [`Error::LayoutMismatch`] if `D` is too small to hold it.
+    ///
+    /// ```c
+    /// const struct {
+    ///     uint64_t magic;
+    ///     const char *name;
+    ///     uint32_t version;
+    ///     int (*init)(void);
+    /// } DYLIB_PLUGIN_DESCRIPTOR = { 0x3147554c504e5944, "example", 1, example_init };
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// `D` must match the layout of the exported descriptor, and pointers
+    /// read from it must not be used once the library is closed
+    pub unsafe fn load_plugin_descriptor<D: Copy>(&self) -> Result<D, Error> {
+        if mem::size_of::<D>() < mem::size_of::<u64>() {
+            return Err(Error::LayoutMismatch {
+                expected: mem::size_of::<u64>(),
+                found: mem::size_of::<D>(),
+            });
+        }
+        let descriptor = unsafe { self.symbol::<D>(PLUGIN_DESCRIPTOR_SYMBOL)? };
+        let magic = unsafe { descriptor.cast::<u64>().read_unaligned() };
+        if magic != PLUGIN_DESCRIPTOR_MAGIC {
+            return Err(Error::DescriptorMagicMismatch { found: magic });
+        }
+        Ok(unsafe { descriptor.read_unaligned() })
+    }
+
+    /// Runs the capability handshake many plugin protocols use: calls the
+    /// function the library exports as `symbol` with the bitmask of
+    /// features the host supports, and returns the features both sides
+    /// support, the intersection of `host_caps` with the mask the plugin
+    /// answers. Bits the plugin claims without the host offering them are
+    /// dropped, and a null export fails with [`Error::NullSymbol`].
+    ///
+    /// ```c
+    /// uint64_t plugin_capabilities(uint64_t host_caps) { return FEATURE_A | FEATURE_B; }
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// `symbol` must be a function of type `extern "C" fn(u64) -> u64`
+    pub unsafe fn negotiate_capabilities(
+        &self,
+        symbol: &str,
+        host_caps: u64,
+    ) -> Result<u64, Error> {
+        let negotiate = unsafe { self.get_fn::<extern "C" fn(u64) -> u64>(symbol)? };
+        Ok(negotiate(host_caps) & host_caps)
+    }
+}
+
+/// Identifies a plugin loaded by a [`PluginManager`]. Ids are never reused
+/// by the same manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PluginId(u64);
+
+type Hook = Box<dyn FnMut(PluginId, &DynamicLibrary)>;
+
+/// Owns the libraries loaded through it, handing out a [`PluginId`] for
+/// each, and runs the registered hooks as they are loaded and unloaded.
+/// A file is loaded at most once, whatever path it is reached through.
+/// Plugins still loaded when the manager is dropped are closed without
+/// running the unload hook
+#[derive(Default)]
+pub struct PluginManager {
+    plugins: BTreeMap<PluginId, DynamicLibrary>,
+    ids: HashMap<PathBuf, PluginId>,
+    next_id: u64,
+    force_reload: bool,
+    on_load: Option<Hook>,
+    on_unload: Option<Hook>,
+}
+
+impl PluginManager {
+    /// Creates a manager without plugins or hooks
+    pub fn new() -> PluginManager {
+        PluginManager::default()
+    }
+
+    /// Sets the hook called with each plugin right after it is loaded
+    pub fn on_load(&mut self, hook: impl FnMut(PluginId, &DynamicLibrary) + 'static) {
+        self.on_load = Some(Box::new(hook));
+    }
+
+    /// Sets the hook called with each plugin right before it is unloaded,
+    /// while its symbols can still be used
+    pub fn on_unload(&mut self, hook: impl FnMut(PluginId, &DynamicLibrary) + 'static) {
+        self.on_unload = Some(Box::new(hook));
+    }
+
+    /// Makes [`PluginManager::load`] reopen a plugin that is already
+    /// loaded, picking up a rebuilt file during development, instead of
+    /// returning it as is
+    pub const fn force_reload(&mut self, force_reload: bool) {
+        self.force_reload = force_reload;
+    }
+
+    /// Lazily opens the library at `path` as a new plugin. When the same
+    /// file, compared by canonical path, is already loaded, returns its id
+    /// instead, after reopening it if [`PluginManager::force_reload`] is on
+    pub fn load(&mut self, path: &Path) -> Result<PluginId, Error> {
+        let path = fs::canonicalize(path).map_err(|err| Error::io(path, &err))?;
+        if let Some(&id) = self.ids.get(&path) {
+            if self.force_reload {
+                self.reload(id)?;
+            }
+            return Ok(id);
+        }
+        let lib = DynamicLibrary::open(Some(&path))?;
+        let id = PluginId(self.next_id);
+        self.next_id += 1;
+        if let Some(hook) = &mut self.on_load {
+            hook(id, &lib);
+        }
+        self.plugins.insert(id, lib);
+        self.ids.insert(path, id);
+        Ok(id)
+    }
+
+    /// Reopens the plugin `id`, running the unload and load hooks around
+    /// it. A plugin that fails to open again is dropped
+    fn reload(&mut self, id: PluginId) -> Result<(), Error> {
+        let Some(lib) = self.plugins.get_mut(&id) else {
+            return Ok(());
+        };
+        if let Some(hook) = &mut self.on_unload {
+            hook(id, lib);
+        }
+        if let Err(err) = lib.reopen() {
+            self.plugins.remove(&id);
+            self.ids.retain(|_, loaded| *loaded != id);
+            return Err(err);
+        }
+        if let Some(hook) = &mut self.on_load {
+            hook(id, lib);
+        }
+        Ok(())
+    }
+
+    /// Runs the unload hook for the plugin `id` and closes it. Unloading a
+    /// plugin that is not loaded does nothing
+    pub fn unload(&mut self, id: PluginId) -> Result<(), Error> {
+        let Some(mut lib) = self.plugins.remove(&id) else {
+            return Ok(());
+        };
+        self.ids.retain(|_, loaded| *loaded != id);
+        if let Some(hook) = &mut self.on_unload {
+            hook(id, &lib);
+        }
+        lib.close()
+    }
+
+    /// Returns the plugin `id`, `None` once it was unloaded
+    pub fn get(&self, id: PluginId) -> Option<&DynamicLibrary> {
+        self.plugins.get(&id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, ffi::CStr, os::unix::fs::symlink, rc::Rc};
+
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn test_plugin_manager() {
+        let first =
+            test_support::build_library("plugin_first", "int plugin_id(void) { return 1; }", &[]);
+        let second =
+            test_support::build_library("plugin_second", "int plugin_id(void) { return 2; }", &[]);
+        let call = |lib: Option<&DynamicLibrary>| match lib
+            .map(|lib| unsafe { lib.symbol::<u8>("plugin_id") })
+        {
+            None => panic!("The plugin is not loaded"),
+            Some(Err(error)) => panic!("Could not find plugin_id: {}", error),
+            Some(Ok(symbol)) => unsafe {
+                mem::transmute::<*mut u8, extern "C" fn() -> i32>(symbol)()
+            },
+        };
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut manager = PluginManager::new();
+        let loaded = Rc::clone(&events);
+        manager.on_load(move |id, _| loaded.borrow_mut().push(("load", id)));
+        let unloaded = Rc::clone(&events);
+        manager.on_unload(move |id, lib| {
+            assert!(unsafe { lib.symbol::<u8>("plugin_id") }.is_ok());
+            unloaded.borrow_mut().push(("unload", id));
+        });
+
+        let (first, second) = match (manager.load(&first), manager.load(&second)) {
+            (Ok(first), Ok(second)) => (first, second),
+            (Err(error), _) | (_, Err(error)) => panic!("Could not load the plugins: {}", error),
+        };
+        assert_ne!(first, second);
+        assert_eq!(call(manager.get(first)), 1);
+
+        assert_eq!(manager.unload(first), Ok(()));
+        assert!(manager.get(first).is_none());
+        assert_eq!(manager.unload(first), Ok(()));
+        assert_eq!(call(manager.get(second)), 2);
+        assert_eq!(
+            *events.borrow(),
+            [("load", first), ("load", second), ("unload", first)]
+        );
+    }
+
+    #[test]
+    fn test_plugin_manager_dedup() {
+        let source = |version: i32| format!("int plugin_generation(void) {{ return {version}; }}");
+        let path = test_support::build_library("plugin_dedup", &source(1), &[]);
+        let link = test_support::fixture_dir().join("libplugin_dedup_link.so");
+        let _ = fs::remove_file(&link);
+        if let Err(error) = symlink(&path, &link) {
+            panic!("Could not link the fixture: {}", error);
+        }
+        let generation = |lib: Option<&DynamicLibrary>| match lib
+            .map(|lib| unsafe { lib.symbol::<u8>("plugin_generation") })
+        {
+            None => panic!("The plugin is not loaded"),
+            Some(Err(error)) => panic!("Could not find plugin_generation: {}", error),
+            Some(Ok(symbol)) => unsafe {
+                mem::transmute::<*mut u8, extern "C" fn() -> i32>(symbol)()
+            },
+        };
+
+        let mut manager = PluginManager::new();
+        let loads = Rc::new(RefCell::new(0));
+        let counter = Rc::clone(&loads);
+        manager.on_load(move |_, _| *counter.borrow_mut() += 1);
+        let (first, again) = match (manager.load(&path), manager.load(&link)) {
+            (Ok(first), Ok(again)) => (first, again),
+            (Err(error), _) | (_, Err(error)) => panic!("Could not load the plugin: {}", error),
+        };
+        assert_eq!(first, again);
+        assert_eq!(*loads.borrow(), 1);
+        assert_eq!(generation(manager.get(first)), 1);
+
+        test_support::build_library("plugin_dedup", &source(2), &[]);
+        manager.force_reload(true);
+        assert_eq!(manager.load(&path), Ok(first));
+        assert_eq!(*loads.borrow(), 2);
+        assert_eq!(generation(manager.get(first)), 2);
+    }
+
+    #[test]
+    fn test_load_plugin_descriptor() {
+        #[derive(Clone, Copy)]
+        #[repr(C)]
+        struct Descriptor {
+            magic: u64,
+            name: *const std::ffi::c_char,
+            version: u32,
+            init: extern "C" fn() -> i32,
+        }
+
+        let source = |magic: &str| {
+            format!(
+                "#include <stdint.h>\n\
+                 static int described_init(void) {{ return 7; }}\n\
+                 const struct {{\n\
+                     uint64_t magic;\n\
+                     const char *name;\n\
+                     uint32_t version;\n\
+                     int (*init)(void);\n\
+                 }} DYLIB_PLUGIN_DESCRIPTOR = {{ {magic}, \"described\", 3, described_init }};\n"
+            )
+        };
+        let open = |name: &str, magic: &str| {
+            let path = test_support::build_library(name, &source(magic), &[]);
+            match DynamicLibrary::open(Some(&path)) {
+                Err(error) => panic!("Could not load the fixture: {}", error),
+                Ok(lib) => lib,
+            }
+        };
+
+        let lib = open("described", "0x3147554c504e5944");
+        match unsafe { lib.load_plugin_descriptor::<Descriptor>() } {
+            Err(error) => panic!("Could not read the descriptor: {}", error),
+            Ok(descriptor) => {
+                assert_eq!(descriptor.magic, PLUGIN_DESCRIPTOR_MAGIC);
+                assert_eq!(unsafe { CStr::from_ptr(descriptor.name) }, c"described");
+                assert_eq!(descriptor.version, 3);
+                assert_eq!((descriptor.init)(), 7);
+            }
+        }
+        assert!(matches!(
+            unsafe { lib.load_plugin_descriptor::<u32>() },
+            Err(Error::LayoutMismatch { .. })
+        ));
+
+        let lib = open("misdescribed", "0x1234");
+        assert_eq!(
+            unsafe { lib.load_plugin_descriptor::<Descriptor>() }.err(),
+            Some(Error::DescriptorMagicMismatch { found: 0x1234 })
+        );
+    }
+
+    #[test]
+    fn test_negotiate_capabilities() {
+        let path = test_support::build_library(
+            "negotiating",
+            "#include <stdint.h>\n\
+             uint64_t plugin_capabilities(uint64_t host) { return host ? 0x16 : 0; }\n\
+             __asm__(\".globl null_capabilities\\n.set null_capabilities, 0\");\n",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        let negotiate =
+            |host_caps| unsafe { lib.negotiate_capabilities("plugin_capabilities", host_caps) };
+        assert_eq!(negotiate(0b1011), Ok(0b0010));
+        assert_eq!(negotiate(0xff), Ok(0x16));
+        assert_eq!(negotiate(0), Ok(0));
+        assert!(unsafe { lib.negotiate_capabilities("missing_capabilities", 1) }.is_err());
+        assert_eq!(
+            unsafe { lib.negotiate_capabilities("null_capabilities", 1) },
+            Err(Error::NullSymbol {
+                name: "null_capabilities".to_string()
+            })
+        );
+    }
+}
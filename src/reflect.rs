@@ -0,0 +1,85 @@
+//! A by-name view of a library's exports for hosts that look functions up
+//! at run time, such as scripting engines embedding native plugins
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use crate::{DynamicLibrary, Error, FnPtr};
+
+/// The exports of a library, listed once, retrieved by name as typed
+/// functions. Holds a reference to the library so the functions stay valid
+/// as long as the reflection lives
+pub struct LibraryReflection {
+    lib: Arc<DynamicLibrary>,
+    exports: BTreeSet<String>,
+}
+
+impl DynamicLibrary {
+    /// Lists the library's exports into a [`LibraryReflection`] sharing
+    /// ownership of the library
+    pub fn reflect(self: &Arc<Self>) -> Result<LibraryReflection, Error> {
+        Ok(LibraryReflection {
+            lib: Arc::clone(self),
+            exports: self.matching_exports(|_| true)?.into_iter().collect(),
+        })
+    }
+}
+
+impl LibraryReflection {
+    /// Reports whether the library exports `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.exports.contains(name)
+    }
+
+    /// The names of the library's exports, sorted
+    pub fn exports(&self) -> impl Iterator<Item = &str> {
+        self.exports.iter().map(String::as_str)
+    }
+
+    /// The library the exports were listed from
+    pub const fn library(&self) -> &Arc<DynamicLibrary> {
+        &self.lib
+    }
+
+    /// Returns the export `name` as the function pointer type `F`, or `None`
+    /// if the library does not export it or its value is null
+    ///
+    /// # Safety
+    ///
+    /// `F` must be the exact function-pointer type of the export, and the
+    /// pointer must not be called after the library is closed
+    pub unsafe fn function<F: FnPtr>(&self, name: &str) -> Option<F> {
+        if !self.contains(name) {
+            return None;
+        }
+        unsafe { self.lib.get_fn(name) }.ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn test_reflect() {
+        let libm = match DynamicLibrary::open(Some(Path::new("libm.so.6"))) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(libm) => Arc::new(libm),
+        };
+        let reflection = match libm.reflect() {
+            Err(error) => panic!("Could not reflect the math library: {}", error),
+            Ok(reflection) => reflection,
+        };
+        drop(libm);
+
+        assert!(reflection.contains("cos"));
+        assert!(!reflection.contains("not_a_math_function"));
+        assert!(reflection.exports().any(|name| name == "sin"));
+        let Some(cos) = (unsafe { reflection.function::<extern "C" fn(f64) -> f64>("cos") }) else {
+            panic!("Could not get cos from the reflection");
+        };
+        assert_eq!(cos(0.0), 1.0);
+        assert!(unsafe { reflection.function::<extern "C" fn()>("not_a_math_function") }.is_none());
+    }
+}
@@ -0,0 +1,171 @@
+//! Prediction of the file the loader picks for a bare library name, following
+//! the same steps as glibc's `ld.so` without loading anything
+
+use std::{
+    env,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{DynamicLibrary, Error, elf::ElfFile, runpath};
+
+const CACHE_PATH: &str = "/etc/ld.so.cache";
+const CACHE_MAGIC: &[u8] = b"glibc-ld.so.cache1.1";
+const CACHE_HEADER_SIZE: usize = 48;
+const CACHE_ENTRY_SIZE: usize = 24;
+
+#[cfg(target_pointer_width = "64")]
+const DEFAULT_DIRS: &[&str] = &["/lib64", "/usr/lib64", "/lib", "/usr/lib"];
+#[cfg(target_pointer_width = "32")]
+const DEFAULT_DIRS: &[&str] = &["/lib", "/usr/lib"];
+
+/// Predicts the canonical path of the file `dlopen(name)` would load, looking
+/// in the search path, then in the `ld.so` cache, then in the default
+/// directories, and skipping files built for another machine. Returns `None`
+/// when nothing matches. Names containing a `/` are taken as paths.
+///
+/// The `DT_RUNPATH` of the calling object is not taken into account.
+pub fn resolve_library_path(name: &str) -> Result<Option<PathBuf>, Error> {
+    if name.contains('/') {
+        return Ok(fs::canonicalize(name).ok());
+    }
+    let exe = env::current_exe().map_err(|err| Error::io(Path::new("current_exe"), &err))?;
+    let process = ElfFile::read_header(&exe)?;
+    let loadable =
+        |path: &Path| ElfFile::read_header(path).is_ok_and(|library| library.same_target(&process));
+
+    let search_path = DynamicLibrary::search_path()
+        .into_iter()
+        .map(|dir| dir.join(name));
+    let cached = cache_entries(name).into_iter();
+    let defaults = DEFAULT_DIRS.iter().map(|dir| Path::new(dir).join(name));
+    Ok(search_path
+        .chain(cached)
+        .chain(defaults)
+        .find(|path| loadable(path))
+        .and_then(|path| fs::canonicalize(path).ok()))
+}
+
+/// Describes, one numbered line each, where the loader looks for the bare
+/// library name `name`, in order: the `LD_LIBRARY_PATH` entries, the
+/// runpath of the executable, the `ld.so` cache entries for `name` and the
+/// default directories, each marked as existing or missing. A debugging aid
+/// for failed loads.
+///
+/// The search path is read from the current environment, while glibc only
+/// honours the value the process started with.
+pub fn explain_search(name: &str) -> String {
+    let mut explanation = format!("search order for `{name}`:\n");
+    if name.contains('/') {
+        explanation.push_str("  the name contains a `/`, so it is opened as a path\n");
+        return explanation;
+    }
+    let runpath = env::current_exe()
+        .ok()
+        .and_then(|exe| runpath(&exe).ok())
+        .unwrap_or_default();
+    let cached = cache_entries(name);
+    let steps = DynamicLibrary::search_path()
+        .into_iter()
+        .map(|dir| (DynamicLibrary::envvar(), dir))
+        .chain(runpath.into_iter().map(|dir| ("runpath", dir)))
+        .chain(cached.into_iter().map(|path| ("ld.so cache", path)))
+        .chain(
+            DEFAULT_DIRS
+                .iter()
+                .map(|dir| ("default directory", PathBuf::from(dir))),
+        );
+    for (number, (source, path)) in steps.enumerate() {
+        let state = if path.exists() { "exists" } else { "missing" };
+        let _ = writeln!(
+            explanation,
+            "  {}. {source}: {} ({state})",
+            number + 1,
+            path.display()
+        );
+    }
+    explanation
+}
+
+/// Returns the paths `/etc/ld.so.cache` records for `name`, in cache order.
+/// A missing or unreadable cache has no entries, as for the loader
+fn cache_entries(name: &str) -> Vec<PathBuf> {
+    let Ok(data) = fs::read(CACHE_PATH) else {
+        return Vec::new();
+    };
+    // Older caches prepend the `ld.so-1.7.0` format; string offsets are
+    // relative to the start of the current format's header
+    let Some(start) = data
+        .windows(CACHE_MAGIC.len())
+        .position(|window| window == CACHE_MAGIC)
+    else {
+        return Vec::new();
+    };
+    let cache = &data[start..];
+    let u32_at = |at: usize| {
+        cache
+            .get(at..at + 4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u32::from_ne_bytes)
+    };
+    let string_at = |at: u32| {
+        cache
+            .get(at as usize..)
+            .and_then(|tail| tail.split(|byte| *byte == 0).next())
+    };
+    let Some(count) = u32_at(CACHE_MAGIC.len()) else {
+        return Vec::new();
+    };
+    (0..count as usize)
+        .map_while(|i| {
+            let at = CACHE_HEADER_SIZE + i * CACHE_ENTRY_SIZE;
+            Some((u32_at(at + 4)?, u32_at(at + 8)?))
+        })
+        .filter(|&(key, _)| string_at(key) == Some(name.as_bytes()))
+        .filter_map(|(_, value)| string_at(value))
+        .map(|value| PathBuf::from(String::from_utf8_lossy(value).into_owned()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::{self, ENV_LOCK};
+
+    #[test]
+    fn test_resolve_library_path() {
+        match resolve_library_path("libm.so.6") {
+            Err(error) => panic!("Could not resolve libm: {}", error),
+            Ok(None) => panic!("libm.so.6 did not resolve"),
+            Ok(Some(path)) => {
+                assert!(path.is_absolute());
+                assert!(path.is_file());
+                assert_eq!(fs::canonicalize(&path).ok(), Some(path));
+            }
+        }
+        match resolve_library_path("libdynamite-does-not-exist.so") {
+            Err(error) => panic!("Could not resolve a missing library: {}", error),
+            Ok(path) => assert_eq!(path, None),
+        }
+    }
+
+    #[test]
+    fn test_explain_search() {
+        let dir = test_support::fixture_dir();
+        let missing = dir.join("not-a-directory");
+        let _guard = ENV_LOCK.lock();
+        let snapshot = DynamicLibrary::snapshot_search_path();
+        DynamicLibrary::prepend_search_path(&missing);
+        DynamicLibrary::prepend_search_path(&dir);
+        let explanation = explain_search("libm.so.6");
+        DynamicLibrary::restore_search_path(snapshot);
+
+        assert!(explanation.contains(&format!("1. LD_LIBRARY_PATH: {} (exists)", dir.display())));
+        assert!(explanation.contains(&format!(
+            "2. LD_LIBRARY_PATH: {} (missing)",
+            missing.display()
+        )));
+        assert!(explanation.contains("default directory: /usr/lib"));
+    }
+}
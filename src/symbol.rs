@@ -0,0 +1,264 @@
+//! Symbols borrowed from a library, in the style of `libloading`
+
+use std::{fmt, marker::PhantomData, mem, ops::Deref, ptr::NonNull};
+
+use crate::{DynamicLibrary, Error};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The function pointer types a symbol can be resolved as by
+/// [`DynamicLibrary::get_fn`]: `extern "C" fn` and `unsafe extern "C" fn`
+/// taking up to 12 arguments. Sealed, so other pointer-sized types such as
+/// `usize` cannot be mistaken for functions
+pub trait FnPtr: Copy + sealed::Sealed {
+    #[doc(hidden)]
+    unsafe fn from_non_null(pointer: NonNull<u8>) -> Self;
+}
+
+macro_rules! impl_fn_ptr {
+    ($($arg:ident),*) => {
+        impl<R, $($arg),*> sealed::Sealed for extern "C" fn($($arg),*) -> R {}
+        impl<R, $($arg),*> sealed::Sealed for unsafe extern "C" fn($($arg),*) -> R {}
+
+        impl<R, $($arg),*> FnPtr for extern "C" fn($($arg),*) -> R {
+            unsafe fn from_non_null(pointer: NonNull<u8>) -> Self {
+                unsafe { mem::transmute::<*mut u8, Self>(pointer.as_ptr()) }
+            }
+        }
+
+        impl<R, $($arg),*> FnPtr for unsafe extern "C" fn($($arg),*) -> R {
+            unsafe fn from_non_null(pointer: NonNull<u8>) -> Self {
+                unsafe { mem::transmute::<*mut u8, Self>(pointer.as_ptr()) }
+            }
+        }
+    };
+}
+
+impl_fn_ptr!();
+impl_fn_ptr!(A);
+impl_fn_ptr!(A, B);
+impl_fn_ptr!(A, B, C);
+impl_fn_ptr!(A, B, C, D);
+impl_fn_ptr!(A, B, C, D, E);
+impl_fn_ptr!(A, B, C, D, E, G);
+impl_fn_ptr!(A, B, C, D, E, G, H);
+impl_fn_ptr!(A, B, C, D, E, G, H, I);
+impl_fn_ptr!(A, B, C, D, E, G, H, I, J);
+impl_fn_ptr!(A, B, C, D, E, G, H, I, J, K);
+impl_fn_ptr!(A, B, C, D, E, G, H, I, J, K, L);
+impl_fn_ptr!(A, B, C, D, E, G, H, I, J, K, L, M);
+
+/// Turns the address `name` resolved to into the function pointer `F`,
+/// failing with [`Error::NullSymbol`] if it is null, as a function pointer
+/// must never be
+///
+/// # Safety
+///
+/// `F` must match the actual type of the function at `pointer`
+pub(crate) unsafe fn fn_from_symbol<F: FnPtr>(name: &str, pointer: *mut u8) -> Result<F, Error> {
+    match NonNull::new(pointer) {
+        Some(pointer) => Ok(unsafe { F::from_non_null(pointer) }),
+        None => Err(Error::NullSymbol {
+            name: name.to_string(),
+        }),
+    }
+}
+
+/// A pointer-sized value, usually a function pointer, resolved from a
+/// library and only usable while the library is borrowed, see
+/// [`DynamicLibrary::get`]. Dereferences to `T`, so a function can be
+/// called as `sym(args)` or `(*sym)(args)`
+pub struct Symbol<'lib, T> {
+    pointer: *mut u8,
+    pd: PhantomData<&'lib T>,
+}
+
+impl<T> Symbol<'_, T> {
+    /// Returns the address of the symbol. Always `Some`, as
+    /// [`DynamicLibrary::get`] rejects null addresses; kept for parity with
+    /// `libloading`
+    pub fn try_as_raw_ptr(self) -> Option<*mut libc::c_void> {
+        (!self.pointer.is_null()).then_some(self.pointer.cast())
+    }
+}
+
+impl<T> Deref for Symbol<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // `get` checked that `T` has the size of the pointer it stores, and
+        // that the pointer is not null
+        unsafe { &*(&self.pointer as *const *mut u8).cast::<T>() }
+    }
+}
+
+impl<T> Clone for Symbol<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Symbol<'_, T> {}
+
+impl<T> fmt::Debug for Symbol<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Symbol").field(&self.pointer).finish()
+    }
+}
+
+/// A struct of function pointers resolved from a library all at once,
+/// usually implemented with `#[derive(DylibApi)]`, available through the
+/// `derive` feature. The derive takes a struct of named fields whose types
+/// implement [`FnPtr`], rejecting any other at compile time, each resolved
+/// by its name or the one given with `#[dylib(name = "...")]`:
+///
+/// ```ignore
+/// #[derive(DylibApi)]
+/// struct Math {
+///     cos: extern "C" fn(f64) -> f64,
+///     #[dylib(name = "sqrt")]
+///     square_root: extern "C" fn(f64) -> f64,
+/// }
+///
+/// let math = unsafe { Math::load(&libm)? };
+/// ```
+pub trait DylibApi: Sized {
+    /// Resolves every function, failing on the first one that is missing.
+    /// The pointers do not borrow the library, so they must not be called
+    /// once it is closed
+    ///
+    /// # Safety
+    ///
+    /// Each field must match the actual type of the symbol it is resolved
+    /// from
+    unsafe fn load(lib: &DynamicLibrary) -> Result<Self, Error>;
+}
+
+impl DynamicLibrary {
+    /// Resolves `symbol` as a `T` borrowed from the library, where `T` is a
+    /// pointer-sized type such as `extern "C" fn(f64) -> f64`. Fails with
+    /// [`Error::NullSymbol`] if the export's value is null, as a reference
+    /// to a function pointer must not hold one. The name may end with a NUL
+    /// byte, as with `libloading`:
+    ///
+    /// ```
+    /// # use std::path::Path;
+    /// use dynamite::{DynamicLibrary, Symbol};
+    ///
+    /// fn call_dynamic() -> Result<f64, Box<dyn std::error::Error>> {
+    ///     # #[cfg(target_os = "linux")]
+    ///     # let path = Path::new("libm.so.6");
+    ///     # #[cfg(not(target_os = "linux"))]
+    ///     # let path = Path::new("libm.dylib");
+    ///     unsafe {
+    ///         let lib = DynamicLibrary::open(Some(path))?;
+    ///         let func: Symbol<unsafe extern "C" fn(f64) -> f64> = lib.get(b"cos")?;
+    ///         Ok(func(0.0))
+    ///     }
+    /// }
+    /// # assert_eq!(call_dynamic().ok(), Some(1.0));
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// `T` must match the actual type of the symbol
+    pub unsafe fn get<T>(&self, symbol: &[u8]) -> Result<Symbol<'_, T>, Error> {
+        if mem::size_of::<T>() != mem::size_of::<*mut u8>() {
+            return Err(Error::LayoutMismatch {
+                expected: mem::size_of::<*mut u8>(),
+                found: mem::size_of::<T>(),
+            });
+        }
+        let pointer = unsafe { self.symbol_bytes::<u8>(symbol)? };
+        if pointer.is_null() {
+            let name = symbol.strip_suffix(b"\0").unwrap_or(symbol);
+            return Err(Error::NullSymbol {
+                name: String::from_utf8_lossy(name).into_owned(),
+            });
+        }
+        Ok(Symbol {
+            pointer,
+            pd: PhantomData,
+        })
+    }
+
+    /// Resolves `name` as the function pointer type `F`, such as
+    /// `extern "C" fn(f64) -> f64`, copied out of the library so callers
+    /// need no `transmute`. Fails with [`Error::NullSymbol`] if the export's
+    /// value is null. Unlike [`DynamicLibrary::get`] the result does not
+    /// borrow the library, so it must not be called once it is closed
+    ///
+    /// # Safety
+    ///
+    /// `F` must match the actual type of the symbol
+    pub unsafe fn get_fn<F: FnPtr>(&self, name: &str) -> Result<F, Error> {
+        let pointer = unsafe { self.symbol::<u8>(name)? };
+        unsafe { fn_from_symbol(name, pointer) }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn test_get() {
+        let libm = match DynamicLibrary::open(Some(Path::new("libm.so.6"))) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(libm) => libm,
+        };
+        let cos: Symbol<extern "C" fn(f64) -> f64> = match unsafe { libm.get(b"cos\0") } {
+            Err(error) => panic!("Could not load function cos: {}", error),
+            Ok(cos) => cos,
+        };
+        assert_eq!(cos(0.0), 1.0);
+        assert_eq!((*cos)(0.0), 1.0);
+        assert!(cos.try_as_raw_ptr().is_some());
+
+        match unsafe { libm.get::<[u8; 3]>(b"cos") } {
+            Err(Error::LayoutMismatch { .. }) => {}
+            Err(error) => panic!("Expected a layout mismatch, got: {}", error),
+            Ok(_) => panic!("Resolved a symbol as a type of the wrong size."),
+        }
+    }
+
+    #[test]
+    fn test_get_fn() {
+        let libm = match DynamicLibrary::open(Some(Path::new("libm.so.6"))) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(libm) => libm,
+        };
+        match unsafe { libm.get_fn::<extern "C" fn(f64) -> f64>("cos") } {
+            Err(error) => panic!("Could not load function cos: {}", error),
+            Ok(cos) => assert_eq!(cos(0.0), 1.0),
+        }
+        assert!(unsafe { libm.get_fn::<extern "C" fn()>("not_in_libm") }.is_err());
+
+        // An absolute export set to 0 resolves, but to no function
+        let path = test_support::build_library(
+            "null_function",
+            "__asm__(\".globl null_function\\n.set null_function, 0\");",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(
+            unsafe { lib.get_fn::<extern "C" fn()>("null_function") },
+            Err(Error::NullSymbol {
+                name: "null_function".to_string()
+            })
+        );
+        match unsafe { lib.get::<extern "C" fn()>(b"null_function\0") } {
+            Err(Error::NullSymbol { name }) => assert_eq!(name, "null_function"),
+            Err(error) => panic!("Expected a null symbol, got: {}", error),
+            Ok(_) => panic!("Borrowed a null function pointer."),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! A set of symbols resolved once from a library kept alive alongside them
+
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{DynamicLibrary, Error, FnPtr, symbol::fn_from_symbol};
+
+/// Symbols resolved up front from a library with a fixed, documented set of
+/// exports, retrieved by name afterwards. Holds a reference to the library so
+/// the pointers stay valid as long as the table lives
+///
+/// ```no_run
+/// # use std::{path::Path, sync::Arc};
+/// # use dynamite::{DynamicLibrary, SymbolTable};
+/// # fn main() -> Result<(), dynamite::Error> {
+/// let libm = Arc::new(DynamicLibrary::open(Some(Path::new("libm.so.6")))?);
+/// let table = SymbolTable::new(libm).resolve("cos")?.resolve("sin")?;
+/// let cos = unsafe { table.get_fn::<extern "C" fn(f64) -> f64>("cos") };
+/// # Ok(())
+/// # }
+/// ```
+pub struct SymbolTable {
+    lib: Arc<DynamicLibrary>,
+    symbols: HashMap<String, *mut u8>,
+}
+
+impl SymbolTable {
+    /// Creates an empty table over `lib`
+    pub fn new(lib: Arc<DynamicLibrary>) -> SymbolTable {
+        SymbolTable {
+            lib,
+            symbols: HashMap::new(),
+        }
+    }
+
+    /// Resolves `name` in the library and adds it to the table
+    pub fn resolve(mut self, name: &str) -> Result<SymbolTable, Error> {
+        let symbol = unsafe { self.lib.symbol::<u8>(name)? };
+        self.symbols.insert(name.to_string(), symbol);
+        Ok(self)
+    }
+
+    /// The library the symbols were resolved from
+    pub const fn library(&self) -> &Arc<DynamicLibrary> {
+        &self.lib
+    }
+
+    /// Returns the symbol `name` as the function pointer type `F`, or `None`
+    /// if it was not resolved or its value is null
+    ///
+    /// # Safety
+    ///
+    /// `F` must be the exact function-pointer type of the export, and the
+    /// pointer must not be called after the library is closed
+    pub unsafe fn get_fn<F: FnPtr>(&self, name: &str) -> Option<F> {
+        let symbol = *self.symbols.get(name)?;
+        unsafe { fn_from_symbol(name, symbol) }.ok()
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn test_symbol_table() {
+        let libm = match DynamicLibrary::open(Some(Path::new("libm.so.6"))) {
+            Err(error) => panic!("Could not load the math library: {}", error),
+            Ok(libm) => Arc::new(libm),
+        };
+        let table = SymbolTable::new(Arc::clone(&libm))
+            .resolve("cos")
+            .and_then(|table| table.resolve("sin"));
+        let table = match table {
+            Err(error) => panic!("Could not build the symbol table: {}", error),
+            Ok(table) => table,
+        };
+        drop(libm);
+
+        let cos = unsafe { table.get_fn::<extern "C" fn(f64) -> f64>("cos") };
+        let sin = unsafe { table.get_fn::<extern "C" fn(f64) -> f64>("sin") };
+        let (Some(cos), Some(sin)) = (cos, sin) else {
+            panic!("Resolved functions are missing from the table");
+        };
+        assert_eq!(cos(0.0), 1.0);
+        assert_eq!(sin(0.0), 0.0);
+        assert!(unsafe { table.get_fn::<extern "C" fn(f64) -> f64>("tan") }.is_none());
+
+        if SymbolTable::new(Arc::clone(table.library()))
+            .resolve("not_a_math_function")
+            .is_ok()
+        {
+            panic!("Resolved a missing symbol.");
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Helpers shared by the unit tests: an environment lock and C fixture
+//! libraries compiled on demand with the system C compiler
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+
+/// Serializes the tests that mutate the process environment
+pub static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// Directory the fixture libraries are built into
+pub fn fixture_dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/fixtures");
+    if let Err(error) = fs::create_dir_all(&dir) {
+        panic!("Could not create {}: {}", dir.display(), error);
+    }
+    dir
+}
+
+/// Compiles the C `source` into the shared library `lib<name>` inside the
+/// fixture directory, passing `args` to the compiler, and returns its path
+pub fn build_library(name: &str, source: &str, args: &[&str]) -> PathBuf {
+    build_library_in(&fixture_dir(), name, source, args)
+}
+
+/// Same as [`build_library`] but places the library in `dir`
+pub fn build_library_in(dir: &Path, name: &str, source: &str, args: &[&str]) -> PathBuf {
+    if let Err(error) = fs::create_dir_all(dir) {
+        panic!("Could not create {}: {}", dir.display(), error);
+    }
+    let source_path = dir.join(format!("{name}.c"));
+    if let Err(error) = fs::write(&source_path, source) {
+        panic!("Could not write {}: {}", source_path.display(), error);
+    }
+    let library = dir.join(format!(
+        "{}{name}{}",
+        env::consts::DLL_PREFIX,
+        env::consts::DLL_SUFFIX
+    ));
+    // Build next to the final path and rename so a concurrent reader never
+    // observes a partially written library
+    let partial = dir.join(format!("{name}.partial"));
+    let output = Command::new(env::var_os("CC").unwrap_or_else(|| "cc".into()))
+        .args(["-shared", "-fPIC", "-o"])
+        .arg(&partial)
+        .arg(&source_path)
+        .args(args)
+        .output();
+    match output {
+        Err(error) => panic!("Could not run the C compiler: {}", error),
+        Ok(output) if !output.status.success() => panic!(
+            "Could not build fixture {}: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Ok(_) => {}
+    }
+    if let Err(error) = fs::rename(&partial, &library) {
+        panic!("Could not move {}: {}", partial.display(), error);
+    }
+    library
+}
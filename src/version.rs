@@ -0,0 +1,110 @@
+//! Semantic versions exported by plugins as C strings
+
+use std::ffi::{CStr, c_char};
+
+use semver::{Version, VersionReq};
+
+use crate::{DynamicLibrary, Error};
+
+impl DynamicLibrary {
+    /// Reads the version a plugin exports as `const char *symbol = "1.2.3";`
+    /// and parses it as a semantic version. Fails with
+    /// [`Error::NullSymbol`] if the export's address is null
+    ///
+    /// # Safety
+    ///
+    /// `symbol` must be a `const char *` that is null or points to a
+    /// NUL-terminated string, alive while it is read
+    pub unsafe fn plugin_version(&self, symbol: &str) -> Result<Version, Error> {
+        let invalid = |reason: &str| Error::InvalidVersion {
+            name: symbol.to_string(),
+            reason: reason.to_string(),
+        };
+        let version = unsafe {
+            let address = self.symbol::<*const c_char>(symbol)?;
+            if address.is_null() {
+                return Err(Error::NullSymbol {
+                    name: symbol.to_string(),
+                });
+            }
+            let string = *address;
+            if string.is_null() {
+                return Err(invalid("null string"));
+            }
+            CStr::from_ptr(string)
+        };
+        let version = version.to_str().map_err(|_| invalid("not UTF-8"))?;
+        Version::parse(version.trim()).map_err(|err| invalid(&err.to_string()))
+    }
+
+    /// Fails with [`Error::VersionMismatch`] unless the version read by
+    /// [`DynamicLibrary::plugin_version`] satisfies `req`
+    ///
+    /// # Safety
+    ///
+    /// As for [`DynamicLibrary::plugin_version`], `symbol` must be a
+    /// `const char *` that is null or points to a NUL-terminated string
+    pub unsafe fn require_version_req(&self, symbol: &str, req: &VersionReq) -> Result<(), Error> {
+        let version = unsafe { self.plugin_version(symbol)? };
+        if req.matches(&version) {
+            Ok(())
+        } else {
+            Err(Error::VersionMismatch {
+                name: symbol.to_string(),
+                found: version.to_string(),
+                required: req.to_string(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn test_plugin_version() {
+        let path = test_support::build_library(
+            "plugin_version",
+            "const char *PLUGIN_VERSION = \"1.2.3\";\n\
+             const char *BROKEN_VERSION = \"one.two\";\n\
+             __asm__(\".globl NULL_VERSION\\n.set NULL_VERSION, 0\");\n",
+            &[],
+        );
+        let lib = match DynamicLibrary::open(Some(&path)) {
+            Err(error) => panic!("Could not load the fixture: {}", error),
+            Ok(lib) => lib,
+        };
+        assert_eq!(
+            unsafe { lib.plugin_version("PLUGIN_VERSION") },
+            Ok(Version::new(1, 2, 3))
+        );
+        match unsafe { lib.plugin_version("BROKEN_VERSION") } {
+            Err(Error::InvalidVersion { .. }) => {}
+            Err(error) => panic!("Expected an invalid version, got: {}", error),
+            Ok(version) => panic!("Parsed a broken version as {}", version),
+        }
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            unsafe { lib.plugin_version("NULL_VERSION") },
+            Err(Error::NullSymbol {
+                name: "NULL_VERSION".to_string()
+            })
+        );
+
+        let (Ok(compatible), Ok(newer)) = (VersionReq::parse("^1.2"), VersionReq::parse(">=2.0"))
+        else {
+            panic!("Could not parse the version requirements");
+        };
+        assert_eq!(
+            unsafe { lib.require_version_req("PLUGIN_VERSION", &compatible) },
+            Ok(())
+        );
+        match unsafe { lib.require_version_req("PLUGIN_VERSION", &newer) } {
+            Err(Error::VersionMismatch { found, .. }) => assert_eq!(found, "1.2.3"),
+            Err(error) => panic!("Expected a version mismatch, got: {}", error),
+            Ok(()) => panic!("Accepted 1.2.3 as >=2.0"),
+        }
+    }
+}
@@ -0,0 +1,34 @@
+#![cfg(all(feature = "derive", target_os = "linux"))]
+
+use std::path::Path;
+
+use dynamite::{DylibApi, DynamicLibrary};
+
+#[derive(DylibApi)]
+struct Math {
+    cos: extern "C" fn(f64) -> f64,
+    #[dylib(name = "sqrt")]
+    square_root: extern "C" fn(f64) -> f64,
+}
+
+#[derive(DylibApi)]
+struct Incomplete {
+    #[allow(dead_code)]
+    not_in_libm: extern "C" fn(),
+}
+
+#[test]
+fn test_derive_dylib_api() {
+    let libm = match DynamicLibrary::open(Some(Path::new("libm.so.6"))) {
+        Err(error) => panic!("Could not load the math library: {}", error),
+        Ok(libm) => libm,
+    };
+    match unsafe { Math::load(&libm) } {
+        Err(error) => panic!("Could not load the math functions: {}", error),
+        Ok(math) => {
+            assert_eq!((math.cos)(0.0), 1.0);
+            assert_eq!((math.square_root)(9.0), 3.0);
+        }
+    }
+    assert!(unsafe { Incomplete::load(&libm) }.is_err());
+}
@@ -0,0 +1,44 @@
+//! Kept in its own test binary: the policy applies to every library dropped
+//! in the process
+#![cfg(target_os = "linux")]
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+};
+
+use dynamite::{DropErrorPolicy, DynamicLibrary, set_drop_error_policy};
+
+/// Returns a handle to the C library whose reference was already given
+/// back, so closing it fails. The C library is never unloaded, so the
+/// extra close is harmless
+fn overclosed_libc() -> DynamicLibrary {
+    let Ok(mut lib) = DynamicLibrary::open(Some(Path::new("libc.so.6"))) else {
+        panic!("Could not load the C library");
+    };
+    let raw = lib.as_raw();
+    if let Err(error) = lib.close() {
+        panic!("Could not close the C library: {}", error);
+    }
+    match unsafe { DynamicLibrary::from_raw(raw) } {
+        Err(error) => panic!("Could not take back the C library: {}", error),
+        Ok(lib) => lib,
+    }
+}
+
+#[test]
+fn test_drop_error_policy() {
+    match overclosed_libc().close_owned() {
+        Err(error) => assert!(error.to_string().contains("not open")),
+        Ok(()) => panic!("Closed the C library once more than it was opened"),
+    }
+
+    drop(overclosed_libc());
+    set_drop_error_policy(DropErrorPolicy::Print);
+    drop(overclosed_libc());
+
+    set_drop_error_policy(DropErrorPolicy::Panic);
+    let lib = overclosed_libc();
+    assert!(panic::catch_unwind(AssertUnwindSafe(move || drop(lib))).is_err());
+    set_drop_error_policy(DropErrorPolicy::Ignore);
+}
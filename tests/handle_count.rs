@@ -0,0 +1,37 @@
+//! Kept in its own test binary: the count is process-wide, so handles opened
+//! by concurrently running tests would skew it
+
+use dynamite::{DynamicLibrary, open_handle_count};
+
+#[test]
+fn test_open_handle_count() {
+    let baseline = open_handle_count();
+    let (first, second) = match (DynamicLibrary::open(None), DynamicLibrary::open(None)) {
+        (Ok(first), Ok(second)) => (first, second),
+        (Err(error), _) | (_, Err(error)) => panic!("Could not load self as module: {}", error),
+    };
+    assert_eq!(open_handle_count(), baseline + 2);
+
+    drop(first);
+    assert_eq!(open_handle_count(), baseline + 1);
+    second.leak();
+    assert_eq!(open_handle_count(), baseline);
+
+    let Ok(lib) = DynamicLibrary::open(None) else {
+        panic!("Could not load self as module");
+    };
+    let raw = lib.as_raw();
+    assert_eq!(lib.into_raw(), raw);
+    assert_eq!(open_handle_count(), baseline);
+    let lib = match unsafe { DynamicLibrary::from_raw(raw) } {
+        Err(error) => panic!("Could not take back self as module: {}", error),
+        Ok(lib) => lib,
+    };
+    assert_eq!(open_handle_count(), baseline + 1);
+    assert_eq!(lib.as_raw(), raw);
+    assert!(unsafe { lib.symbol::<u8>("malloc") }.is_ok());
+    drop(lib);
+    assert_eq!(open_handle_count(), baseline);
+    assert!(unsafe { DynamicLibrary::from_raw(std::ptr::null_mut()) }.is_err());
+    assert_eq!(open_handle_count(), baseline);
+}
@@ -0,0 +1,56 @@
+//! Kept in its own test binary: the rewriter applies to every open in the
+//! process, so it would redirect the libraries concurrent tests open. Linux
+//! only, as the loaded system libraries are not files on disk on recent macOS
+#![cfg(target_os = "linux")]
+
+use std::{fs, path::Path};
+
+use dynamite::{DynamicLibrary, clear_path_rewriter, loaded_libraries, set_path_rewriter};
+
+#[test]
+fn test_path_rewriter() {
+    // A library the test executable is linked against
+    let Some(real) = loaded_libraries()
+        .into_iter()
+        .skip(1)
+        .map(|image| image.path)
+        .find(|path| path.is_absolute() && path.is_file())
+    else {
+        panic!("Could not find a loaded library");
+    };
+    let (Some(dir), Some(name)) = (real.parent(), real.file_name()) else {
+        panic!("Could not split {}", real.display());
+    };
+    let configured = Path::new("/opt/dynamite-relocated").join(name);
+    assert!(DynamicLibrary::open(Some(&configured)).is_err());
+
+    let target = dir.to_path_buf();
+    set_path_rewriter(
+        move |path| match path.strip_prefix("/opt/dynamite-relocated") {
+            Ok(rest) => target.join(rest),
+            Err(_) => path.to_path_buf(),
+        },
+    );
+    let redirected = DynamicLibrary::open(Some(&configured));
+    let options = DynamicLibrary::options().open(Some(&configured));
+    let secure = DynamicLibrary::open_secure(&configured);
+    clear_path_rewriter();
+
+    match redirected {
+        Err(error) => panic!("Could not open the redirected library: {}", error),
+        Ok(lib) => assert_eq!(lib.opened_path(), Some(real.as_path())),
+    }
+    if let Err(error) = options {
+        panic!(
+            "Could not open the redirected library with options: {}",
+            error
+        );
+    }
+    // The rewritten path is the one checked, then opened as is
+    match (secure, fs::canonicalize(&real)) {
+        (Err(error), _) => panic!("Could not securely open the redirected library: {}", error),
+        (_, Err(error)) => panic!("Could not canonicalize {}: {}", real.display(), error),
+        (Ok(lib), Ok(canonical)) => assert_eq!(lib.opened_path(), Some(canonical.as_path())),
+    }
+    assert!(DynamicLibrary::open(Some(&configured)).is_err());
+}
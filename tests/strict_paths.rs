@@ -0,0 +1,54 @@
+//! Kept in its own test binary: strict mode is process-wide, so it would
+//! reject the bare names concurrently running tests open. Linux only, as
+//! the loaded system libraries are not files on disk on recent macOS
+#![cfg(target_os = "linux")]
+
+use std::path::Path;
+
+use dynamite::{DynamicLibrary, Error, loaded_libraries, set_strict_paths};
+
+#[test]
+fn test_strict_paths() {
+    // A library the test executable is linked against
+    let Some(absolute) = loaded_libraries()
+        .into_iter()
+        .skip(1)
+        .map(|image| image.path)
+        .find(|path| path.is_absolute() && path.is_file())
+    else {
+        panic!("Could not find a loaded library");
+    };
+    let bare = Path::new("libdynamite-strict.so");
+    match DynamicLibrary::open_absolute(bare) {
+        Err(Error::RelativePathRejected(path)) => assert_eq!(path, bare),
+        Err(error) => panic!("Expected a rejected relative path, got: {}", error),
+        Ok(_) => panic!("Opened a bare file name as an absolute path."),
+    }
+
+    set_strict_paths(true);
+    let rejected = DynamicLibrary::open(Some(bare));
+    let relative = DynamicLibrary::options().open(Some(Path::new("./libdynamite-strict.so")));
+    let process = DynamicLibrary::open(None);
+    let loaded = DynamicLibrary::open(Some(&absolute));
+    let preloaded = DynamicLibrary::open_in_new_namespace_with_preload(&absolute, &[bare]);
+    set_strict_paths(false);
+
+    match preloaded {
+        Err(Error::RelativePathRejected(path)) => assert_eq!(path, bare),
+        Err(error) => panic!("Expected a rejected relative preload, got: {}", error),
+        Ok(_) => panic!("Preloaded a relative path in strict mode."),
+    }
+    for result in [rejected, relative] {
+        match result {
+            Err(Error::RelativePathRejected(_)) => {}
+            Err(error) => panic!("Expected a rejected relative path, got: {}", error),
+            Ok(_) => panic!("Opened a relative path in strict mode."),
+        }
+    }
+    if let Err(error) = process {
+        panic!("Could not load self as module in strict mode: {}", error);
+    }
+    if let Err(error) = loaded {
+        panic!("Could not load an absolute path in strict mode: {}", error);
+    }
+}
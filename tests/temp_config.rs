@@ -0,0 +1,70 @@
+//! Kept in its own test binary: the configuration applies to every copy
+//! made in the process, and the rewriter observing the copy to every open.
+//! Linux only, as the loaded system libraries are not files on disk on
+//! recent macOS
+#![cfg(target_os = "linux")]
+
+use std::{
+    env, fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use dynamite::{
+    DynamicLibrary, TempConfig, clear_path_rewriter, loaded_libraries, set_path_rewriter,
+    set_temp_config,
+};
+
+#[test]
+fn test_temp_config() {
+    // A library the test executable is linked against
+    let Some(real) = loaded_libraries()
+        .into_iter()
+        .skip(1)
+        .map(|image| image.path)
+        .find(|path| path.is_absolute() && path.is_file())
+    else {
+        panic!("Could not find a loaded library");
+    };
+    let dir = env::temp_dir().join(format!("dynamite-temp-config-{}", std::process::id()));
+    if let Err(error) = fs::create_dir_all(&dir) {
+        panic!("Could not create {}: {}", dir.display(), error);
+    }
+    set_temp_config(TempConfig {
+        dir: Some(dir.clone()),
+        mode: 0o640,
+    });
+
+    // The copy only exists while it is opened, so it is inspected then
+    let copies: Arc<Mutex<Vec<(PathBuf, u32)>>> = Arc::default();
+    let seen = Arc::clone(&copies);
+    let prefix = dir.clone();
+    set_path_rewriter(move |path| {
+        if path.starts_with(&prefix)
+            && let Ok(metadata) = fs::metadata(path)
+        {
+            let mode = metadata.permissions().mode() & 0o777;
+            seen.lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push((path.to_path_buf(), mode));
+        }
+        path.to_path_buf()
+    });
+    let lib = DynamicLibrary::open_shadow_copy(&real);
+    clear_path_rewriter();
+    set_temp_config(TempConfig::default());
+
+    if let Err(error) = lib {
+        panic!("Could not load the shadow copy: {}", error);
+    }
+    let copies = copies.lock().unwrap_or_else(PoisonError::into_inner);
+    let [(copy, mode)] = copies.as_slice() else {
+        panic!("Expected a single copy, got {:?}", copies);
+    };
+    assert_eq!(copy.parent().and_then(Path::parent), Some(dir.as_path()));
+    assert_eq!(copy.file_name(), real.file_name());
+    assert_eq!(*mode, 0o640);
+    assert!(!copy.exists());
+    let _ = fs::remove_dir_all(&dir);
+}